@@ -0,0 +1,16 @@
+use std::fs;
+
+// The log cache stores serialized LogFiles, so it has to be discarded when the parser changes.
+// The version of ICT_logfile is taken from Cargo.lock and passed to the crate as ICT_LOGFILE_VERSION.
+fn main() {
+    println!("cargo:rerun-if-changed=Cargo.lock");
+
+    let lock = fs::read_to_string("Cargo.lock").unwrap_or_default();
+    let version = lock
+        .split("[[package]]")
+        .find(|p| p.contains("name = \"ICT_logfile\""))
+        .and_then(|p| p.lines().find_map(|l| l.strip_prefix("version = ")))
+        .map_or("unknown", |v| v.trim_matches('"'));
+
+    println!("cargo:rustc-env=ICT_LOGFILE_VERSION={version}");
+}
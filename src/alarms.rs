@@ -0,0 +1,558 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+use chrono::{Local, NaiveDateTime};
+
+use crate::{u64_to_datetime, BResult, LoadedProduct, LogFile};
+
+/*
+Alarm rules, checked while the automatic update is running. Loaded from a text file, one rule per line:
+
+    # first pass yield below 95% in the last 50 boards
+    fpy_below 95 50
+    # the same test failing on 3 boards in a row
+    consecutive_fails 3
+    # no new logs for 20 minutes
+    no_logs 20
+    # optional: beep when an alarm fires, and append the alarms to a file
+    sound on
+    log .\alarm.log
+
+An alarm fires once when its condition becomes true, it can fire again after the condition cleared.
+The rules are checked for each loaded product separately.
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlarmRule {
+    FpyBelow(f32, usize), // limit in %, number of boards
+    ConsecutiveFails(usize),
+    NoLogs(i64), // minutes
+}
+
+impl AlarmRule {
+    fn parse(line: &str) -> Option<Self> {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        match parts[..] {
+            ["fpy_below", limit, boards] => Some(AlarmRule::FpyBelow(
+                limit.parse().ok()?,
+                boards.parse().ok()?,
+            )),
+            ["consecutive_fails", count] => Some(AlarmRule::ConsecutiveFails(count.parse().ok()?)),
+            ["no_logs", minutes] => Some(AlarmRule::NoLogs(minutes.parse().ok()?)),
+            _ => None,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            AlarmRule::FpyBelow(limit, boards) => {
+                format!("First pass yield below {limit}% in the last {boards} boards")
+            }
+            AlarmRule::ConsecutiveFails(count) => {
+                format!("The same test failing {count} times in a row")
+            }
+            AlarmRule::NoLogs(minutes) => format!("No new logs for {minutes} minutes"),
+        }
+    }
+}
+
+pub struct AlarmConfig {
+    pub rules: Vec<AlarmRule>,
+    pub sound: bool,
+    pub log_file: Option<PathBuf>,
+}
+
+impl AlarmConfig {
+    // Loads the alarm rules from 'path'. Without a config file there are no rules.
+    pub fn load(path: &str) -> Self {
+        match fs::read_to_string(path) {
+            Ok(content) => AlarmConfig::parse(&content),
+            Err(_) => {
+                println!("INFO: No alarm config found at {path}, alarms are disabled.");
+                AlarmConfig::parse("")
+            }
+        }
+    }
+
+    fn parse(content: &str) -> Self {
+        let mut ret = AlarmConfig {
+            rules: Vec::new(),
+            sound: false,
+            log_file: None,
+        };
+
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(x) = line.strip_prefix("sound ") {
+                ret.sound = x.trim() == "on";
+            } else if let Some(x) = line.strip_prefix("log ") {
+                ret.log_file = Some(PathBuf::from(x.trim()));
+            } else if let Some(rule) = AlarmRule::parse(line) {
+                ret.rules.push(rule);
+            } else {
+                println!("ERR: Unknown alarm rule: {line}");
+            }
+        }
+
+        ret
+    }
+}
+
+pub struct Alarm {
+    pub time: NaiveDateTime,
+    pub product: String,
+    pub message: String,
+    pub acknowledged: bool,
+}
+
+pub struct AlarmMonitor {
+    pub config: AlarmConfig,
+    pub history: Vec<Alarm>,
+    newest_log: HashMap<String, NaiveDateTime>, // product -> start of its newest log
+    checked: HashSet<String>,                   // products checked at least once
+    live: HashSet<String>,                      // products which got new logs since the first check
+    firing: HashSet<String>,                    // conditions which are currently true
+}
+
+// The parts of a log used by the rules
+trait RuleLog {
+    fn DMC(&self) -> &str;
+    fn time_start(&self) -> u64;
+    fn result(&self) -> BResult;
+    fn failed_tests(&self) -> Vec<String>;
+}
+
+impl RuleLog for LogFile {
+    fn DMC(&self) -> &str {
+        self.get_DMC()
+    }
+
+    fn time_start(&self) -> u64 {
+        self.get_time_start()
+    }
+
+    fn result(&self) -> BResult {
+        self.get_result()
+    }
+
+    fn failed_tests(&self) -> Vec<String> {
+        self.get_failed_tests()
+    }
+}
+
+// First pass yield of the last 'boards' boards, None if there are less boards
+fn recent_fpy<L: RuleLog>(logs: &[&L], boards: usize) -> Option<f32> {
+    let mut first: HashMap<&str, (u64, BResult)> = HashMap::new();
+    for log in logs {
+        let entry = first
+            .entry(log.DMC())
+            .or_insert((log.time_start(), log.result()));
+        if log.time_start() < entry.0 {
+            *entry = (log.time_start(), log.result());
+        }
+    }
+
+    let mut results: Vec<(u64, BResult)> = first.into_values().collect();
+    if boards == 0 || results.len() < boards {
+        return None;
+    }
+    results.sort_by_key(|r| r.0);
+
+    let passed = results[results.len() - boards..]
+        .iter()
+        .filter(|r| r.1 == BResult::Pass)
+        .count();
+    Some(passed as f32 / boards as f32 * 100.0)
+}
+
+// Tests failing on each of the last 'count' boards. 'logs' has to be ordered by time.
+// Every board is counted once, by its first test, so a failing retest isn't counted again.
+fn consecutive_fails<L: RuleLog>(logs: &[&L], count: usize) -> Vec<String> {
+    let mut seen: HashSet<&str> = HashSet::new();
+    let boards: Vec<&L> = logs
+        .iter()
+        .filter(|l| seen.insert(l.DMC()))
+        .copied()
+        .collect();
+
+    if count == 0 || boards.len() < count {
+        return Vec::new();
+    }
+
+    let last = &boards[boards.len() - count..];
+    let mut common = last[0].failed_tests();
+    for log in &last[1..] {
+        let failed = log.failed_tests();
+        common.retain(|t| failed.contains(t));
+    }
+
+    common
+}
+
+impl AlarmMonitor {
+    pub fn new(config: AlarmConfig) -> Self {
+        AlarmMonitor {
+            config,
+            history: Vec::new(),
+            newest_log: HashMap::new(),
+            checked: HashSet::new(),
+            live: HashSet::new(),
+            firing: HashSet::new(),
+        }
+    }
+
+    // Called when new logs are loaded manually, the history is kept
+    pub fn clear(&mut self) {
+        self.newest_log.clear();
+        self.checked.clear();
+        self.live.clear();
+        self.firing.clear();
+    }
+
+    pub fn has_active(&self) -> bool {
+        self.history.iter().any(|a| !a.acknowledged)
+    }
+
+    pub fn active(&self) -> impl Iterator<Item = &Alarm> {
+        self.history.iter().filter(|a| !a.acknowledged)
+    }
+
+    pub fn acknowledge(&mut self) {
+        for alarm in self.history.iter_mut() {
+            alarm.acknowledged = true;
+        }
+    }
+
+    fn fire(&mut self, product: &str, message: String) {
+        let alarm = Alarm {
+            time: Local::now().naive_local(),
+            product: product.to_string(),
+            message,
+            acknowledged: false,
+        };
+        println!("ALARM: {} - {}", alarm.product, alarm.message);
+
+        if let Some(path) = &self.config.log_file {
+            let result = fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .and_then(|mut f| {
+                    writeln!(
+                        f,
+                        "{}\t{}\t{}",
+                        alarm.time.format("%F %T"),
+                        alarm.product,
+                        alarm.message
+                    )
+                });
+            if let Err(e) = result {
+                println!("ERR: Failed to write alarm log {}: {e}", path.display());
+            }
+        }
+
+        if self.config.sound {
+            std::thread::spawn(|| {
+                let _ = std::process::Command::new("powershell")
+                    .args(["-NoProfile", "-Command", "[console]::beep(880,700)"])
+                    .status();
+            });
+        }
+
+        self.history.push(alarm);
+    }
+
+    // Fires the conditions which just became true, and forgets the ones which cleared.
+    // Only the conditions starting with 'prefix' are updated.
+    fn update_conditions(&mut self, prefix: &str, conditions: Vec<(String, String, String)>) {
+        let keys: HashSet<String> = conditions.iter().map(|c| c.0.clone()).collect();
+        self.firing
+            .retain(|k| !k.starts_with(prefix) || keys.contains(k));
+
+        for (key, product, message) in conditions {
+            if self.firing.insert(key) {
+                self.fire(&product, message);
+            }
+        }
+    }
+
+    // Checks the rules on the logs, after new logs were pushed.
+    // The first check of a product only records the state of the already loaded logs,
+    // so opening a past day doesn't fire alarms. Alarms only fire on logs newer than that.
+    pub fn check_logs(&mut self, products: &[LoadedProduct]) {
+        let mut conditions: Vec<(String, String, String)> = Vec::new();
+        let mut baseline: Vec<String> = Vec::new();
+
+        for product in products {
+            let lfh = product.lfh.read().unwrap();
+            let mut logs = lfh.get_logs();
+            logs.sort_by_key(|l| l.get_time_start());
+
+            let newest = logs
+                .last()
+                .and_then(|l| u64_to_datetime(l.get_time_start()));
+            let previous = self.newest_log.get(&product.name).copied();
+            if let Some(t) = newest {
+                self.newest_log.insert(product.name.clone(), t);
+            }
+
+            let first_check = self.checked.insert(product.name.clone());
+            if !first_check && newest > previous {
+                self.live.insert(product.name.clone());
+            }
+
+            let first_condition = conditions.len();
+
+            for rule in &self.config.rules {
+                match rule {
+                    AlarmRule::FpyBelow(limit, boards) => {
+                        if let Some(fpy) = recent_fpy(&logs, *boards) {
+                            if fpy < *limit {
+                                conditions.push((
+                                    format!("logs:{}:fpy", product.name),
+                                    product.name.clone(),
+                                    format!(
+                                        "First pass yield is {fpy:.1}% in the last {boards} boards (limit: {limit}%)"
+                                    ),
+                                ));
+                            }
+                        }
+                    }
+                    AlarmRule::ConsecutiveFails(count) => {
+                        for test in consecutive_fails(&logs, *count) {
+                            conditions.push((
+                                format!("logs:{}:consecutive:{}", product.name, test),
+                                product.name.clone(),
+                                format!("{test} failed {count} times in a row"),
+                            ));
+                        }
+                    }
+                    AlarmRule::NoLogs(_) => (),
+                }
+            }
+
+            if first_check {
+                baseline.extend(conditions[first_condition..].iter().map(|c| c.0.clone()));
+            }
+        }
+
+        // Already true on the first check: marked as firing, without an alarm
+        self.firing.extend(baseline);
+        self.update_conditions("logs:", conditions);
+    }
+
+    // Checks the "no logs" rules, it is cheap enough to call it every frame.
+    pub fn check_idle(&mut self) {
+        let now = Local::now().naive_local();
+        let mut conditions: Vec<(String, String, String)> = Vec::new();
+
+        for rule in &self.config.rules {
+            if let AlarmRule::NoLogs(minutes) = rule {
+                // Only products with new logs since the first check, a past day is always "idle"
+                for (product, newest) in self
+                    .newest_log
+                    .iter()
+                    .filter(|(p, _)| self.live.contains(*p))
+                {
+                    if (now - *newest).num_minutes() >= *minutes {
+                        conditions.push((
+                            format!("idle:{}", product),
+                            product.clone(),
+                            format!(
+                                "No new logs for {minutes} minutes (last: {})",
+                                newest.format("%T")
+                            ),
+                        ));
+                    }
+                }
+            }
+        }
+
+        self.update_conditions("idle:", conditions);
+    }
+}
+
+// Red banner with the unacknowledged alarms. Returns true if the history was requested.
+pub fn alarm_banner(ctx: &egui::Context, monitor: &mut AlarmMonitor, lang: usize) -> bool {
+    let mut show_history = false;
+
+    egui::TopBottomPanel::top("alarm banner")
+        .frame(
+            egui::Frame::default()
+                .fill(egui::Color32::DARK_RED)
+                .inner_margin(6.0),
+        )
+        .show(ctx, |ui| {
+            for alarm in monitor.active() {
+                ui.label(
+                    egui::RichText::new(format!(
+                        "{}  {}: {}",
+                        alarm.time.format("%T"),
+                        alarm.product,
+                        alarm.message
+                    ))
+                    .color(egui::Color32::WHITE)
+                    .size(16.0),
+                );
+            }
+
+            ui.horizontal(|ui| {
+                if ui.button(crate::MESSAGE_A[crate::ACK][lang]).clicked() {
+                    monitor.acknowledge();
+                }
+                if ui.button(crate::MESSAGE_A[crate::HISTORY][lang]).clicked() {
+                    show_history = true;
+                }
+            });
+        });
+
+    show_history
+}
+
+// Rules and the alarm history
+pub fn alarms_ui(ui: &mut egui::Ui, monitor: &AlarmMonitor, lang: usize) {
+    ui.heading(crate::MESSAGE_A[crate::RULES][lang]);
+    if monitor.config.rules.is_empty() {
+        ui.label(crate::MESSAGE_A[crate::NO_RULES][lang]);
+    }
+    for rule in &monitor.config.rules {
+        ui.label(rule.describe());
+    }
+
+    ui.separator();
+    ui.heading(crate::MESSAGE_A[crate::HISTORY][lang]);
+
+    egui::Grid::new("alarm history")
+        .striped(true)
+        .show(ui, |ui| {
+            for alarm in monitor.history.iter().rev() {
+                ui.monospace(alarm.time.format("%F %T").to_string());
+                ui.label(&alarm.product);
+                ui.label(&alarm.message);
+                ui.end_row();
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestLog {
+        DMC: &'static str,
+        time_start: u64,
+        failed: Vec<String>,
+    }
+
+    impl RuleLog for TestLog {
+        fn DMC(&self) -> &str {
+            self.DMC
+        }
+
+        fn time_start(&self) -> u64 {
+            self.time_start
+        }
+
+        fn result(&self) -> BResult {
+            if self.failed.is_empty() {
+                BResult::Pass
+            } else {
+                BResult::Fail
+            }
+        }
+
+        fn failed_tests(&self) -> Vec<String> {
+            self.failed.clone()
+        }
+    }
+
+    fn log(DMC: &'static str, time_start: u64, failed: &[&str]) -> TestLog {
+        TestLog {
+            DMC,
+            time_start,
+            failed: failed.iter().map(|t| t.to_string()).collect(),
+        }
+    }
+
+    fn monitor() -> AlarmMonitor {
+        AlarmMonitor::new(AlarmConfig::parse(""))
+    }
+
+    #[test]
+    fn config_is_parsed() {
+        let config = AlarmConfig::parse(
+            "# comment\nfpy_below 95 50\nconsecutive_fails 3\nno_logs 20\nsound on\nlog alarm.log\nbad rule\n",
+        );
+
+        assert_eq!(
+            config.rules,
+            [
+                AlarmRule::FpyBelow(95.0, 50),
+                AlarmRule::ConsecutiveFails(3),
+                AlarmRule::NoLogs(20)
+            ]
+        );
+        assert!(config.sound);
+        assert_eq!(config.log_file, Some(PathBuf::from("alarm.log")));
+    }
+
+    #[test]
+    fn fpy_counts_the_first_test_of_the_last_boards() {
+        let logs = [
+            log("B1", 1, &["R1"]),
+            log("B2", 2, &["R1"]),
+            log("B2", 3, &[]),
+            log("B3", 4, &[]),
+            log("B4", 5, &[]),
+        ];
+        let logs: Vec<&TestLog> = logs.iter().collect();
+
+        assert_eq!(recent_fpy(&logs, 3), Some(2.0 / 3.0 * 100.0));
+        assert_eq!(recent_fpy(&logs, 4), Some(50.0));
+        assert_eq!(recent_fpy(&logs, 5), None);
+    }
+
+    #[test]
+    fn consecutive_fails_count_each_board_once() {
+        let logs = [
+            log("B1", 1, &["R1", "C2"]),
+            log("B2", 2, &["R1"]),
+            log("B2", 3, &["R1"]),
+            log("B3", 4, &["R1", "C2"]),
+        ];
+        let logs: Vec<&TestLog> = logs.iter().collect();
+
+        assert_eq!(consecutive_fails(&logs, 3), ["R1"]);
+        assert!(consecutive_fails(&logs, 4).is_empty());
+        assert!(consecutive_fails(&logs[1..3], 2).is_empty());
+    }
+
+    #[test]
+    fn alarms_fire_once_until_cleared() {
+        let mut monitor = monitor();
+        let condition = || vec![("logs:P:fpy".to_string(), "P".to_string(), "low".to_string())];
+
+        monitor.update_conditions("logs:", condition());
+        monitor.update_conditions("logs:", condition());
+        assert_eq!(monitor.history.len(), 1);
+
+        // Other prefixes don't clear it
+        monitor.update_conditions("idle:", Vec::new());
+        monitor.update_conditions("logs:", condition());
+        assert_eq!(monitor.history.len(), 1);
+
+        monitor.update_conditions("logs:", Vec::new());
+        monitor.update_conditions("logs:", condition());
+        assert_eq!(monitor.history.len(), 2);
+        assert!(monitor.has_active());
+
+        monitor.acknowledge();
+        assert!(!monitor.has_active());
+    }
+}
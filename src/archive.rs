@@ -0,0 +1,480 @@
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate};
+use std::{
+    fmt, fs,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    thread,
+};
+use zip::write::SimpleFileOptions;
+
+const ARCHIVE_LOG: &str = ".\\archive.log";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArchiveLayout {
+    Day,   // 2024_06_01
+    Week,  // 2024_W22
+    Month, // 2024_06
+}
+
+impl ArchiveLayout {
+    fn dir_name(&self, t: DateTime<Local>) -> String {
+        match self {
+            ArchiveLayout::Day => t.format("%Y_%m_%d").to_string(),
+            ArchiveLayout::Week => t.format("%G_W%V").to_string(),
+            ArchiveLayout::Month => t.format("%Y_%m").to_string(),
+        }
+    }
+}
+
+// Returns the first and last day covered by an archive directory, for any of the layouts.
+pub fn parse_archive_dir(name: &str) -> Option<(NaiveDate, NaiveDate)> {
+    if let Ok(day) = NaiveDate::parse_from_str(name, "%Y_%m_%d") {
+        return Some((day, day));
+    }
+
+    if let Ok(monday) = NaiveDate::parse_from_str(&format!("{name}_1"), "%G_W%V_%u") {
+        return Some((monday, monday + Duration::try_days(6).unwrap()));
+    }
+
+    if let Ok(first) = NaiveDate::parse_from_str(&format!("{name}_01"), "%Y_%m_%d") {
+        let next_month = if first.month() == 12 {
+            NaiveDate::from_ymd_opt(first.year() + 1, 1, 1)
+        } else {
+            NaiveDate::from_ymd_opt(first.year(), first.month() + 1, 1)
+        }?;
+        return Some((first, next_month.pred_opt()?));
+    }
+
+    None
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ArchiveSettings {
+    pub age_limit: i64, // hours
+    pub layout: ArchiveLayout,
+    pub zip: bool,
+}
+
+impl ArchiveSettings {
+    pub fn default() -> Self {
+        ArchiveSettings {
+            age_limit: 4,
+            layout: ArchiveLayout::Day,
+            zip: false,
+        }
+    }
+}
+
+pub enum ArchiveAction {
+    Move(PathBuf, PathBuf),
+    Zip(PathBuf, PathBuf),
+}
+
+impl fmt::Display for ArchiveAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArchiveAction::Move(from, to) => write!(f, "MOVE {} -> {}", from.display(), to.display()),
+            ArchiveAction::Zip(from, to) => write!(f, "ZIP {} -> {}", from.display(), to.display()),
+        }
+    }
+}
+
+// Collects what would be done, without touching anything. This is also the dry-run preview.
+// Logs (files without extension) older than the age limit get moved to a subdirectory,
+// and if enabled, the archive directories which are completly older than the limit get compressed.
+pub fn plan_archive(base: &Path, settings: &ArchiveSettings) -> Result<Vec<ArchiveAction>, std::io::Error> {
+    let mut ret: Vec<ArchiveAction> = Vec::new();
+    let limit = Local::now() - Duration::try_hours(settings.age_limit).unwrap();
+    let mut archive_dirs: Vec<PathBuf> = Vec::new();
+
+    for file in fs::read_dir(base)? {
+        let file = file?;
+        let path = file.path();
+        if path.is_dir() {
+            if !archive_dirs.contains(&path) {
+                archive_dirs.push(path);
+            }
+        } else if path.is_file() && path.extension().is_none() {
+            if let Ok(x) = path.metadata() {
+                let ct: DateTime<Local> = x.modified().unwrap().into();
+                if ct < limit {
+                    let dir = base.join(settings.layout.dir_name(ct));
+                    ret.push(ArchiveAction::Move(path.clone(), dir.join(path.file_name().unwrap())));
+
+                    if !archive_dirs.contains(&dir) {
+                        archive_dirs.push(dir);
+                    }
+                }
+            }
+        }
+    }
+
+    if settings.zip {
+        for dir in archive_dirs {
+            let Some(name) = dir.file_name().and_then(|f| f.to_str()) else {
+                continue;
+            };
+
+            if let Some((_, last_day)) = parse_archive_dir(name) {
+                if last_day < limit.date_naive() {
+                    let zip_path = dir.with_extension("zip");
+                    ret.push(ArchiveAction::Zip(dir, zip_path));
+                }
+            }
+        }
+    }
+
+    Ok(ret)
+}
+
+fn move_file_to_subdir(from: &Path, to: &Path) -> std::io::Result<()> {
+    if let Some(new_dir) = to.parent() {
+        if !new_dir.exists() {
+            fs::create_dir(new_dir)?;
+        }
+    }
+
+    if to.exists() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::AlreadyExists,
+            "target already exists",
+        ));
+    }
+
+    fs::rename(from, to)
+}
+
+// Adds the content of 'dir' to the archive, subdirectories included, under the name 'prefix'.
+// Returns the number of entries written.
+fn zip_add_dir(
+    zip: &mut zip::ZipWriter<fs::File>,
+    dir: &Path,
+    prefix: &str,
+    options: SimpleFileOptions,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let mut entries = 0;
+
+    for file in fs::read_dir(dir)? {
+        let path = file?.path();
+        let name = format!("{prefix}{}", path.file_name().unwrap().to_string_lossy());
+
+        if path.is_dir() {
+            zip.add_directory(name.as_str(), options)?;
+            entries += 1 + zip_add_dir(zip, &path, &format!("{name}/"), options)?;
+        } else {
+            zip.start_file(name.as_str(), options)?;
+            std::io::copy(&mut fs::File::open(&path)?, zip)?;
+            entries += 1;
+        }
+    }
+
+    Ok(entries)
+}
+
+// Writes the archive, then reads it back to check that every entry is in it.
+fn write_zip(dir: &Path, zip_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let mut zip = zip::ZipWriter::new(fs::File::create(zip_path)?);
+    let options =
+        SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+    let written = zip_add_dir(&mut zip, dir, "", options)?;
+    zip.finish()?;
+
+    let archived = zip::ZipArchive::new(fs::File::open(zip_path)?)?.len();
+    if archived != written {
+        return Err(format!(
+            "{} has {archived} entries instead of {written}",
+            zip_path.display()
+        )
+        .into());
+    }
+
+    Ok(())
+}
+
+// Compresses 'dir' into 'zip_path', then removes the directory.
+// The directory is only removed if the archive was verified, a failed archive is deleted instead.
+fn zip_dir(dir: &Path, zip_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    if zip_path.exists() {
+        return Err(format!("{} already exists", zip_path.display()).into());
+    }
+
+    if let Err(e) = write_zip(dir, zip_path) {
+        let _ = fs::remove_file(zip_path);
+        return Err(e);
+    }
+
+    fs::remove_dir_all(dir)?;
+
+    Ok(())
+}
+
+// Executes the actions, and appends them to the archive log.
+fn execute_archive(actions: Vec<ArchiveAction>, messages: Arc<RwLock<Vec<String>>>) {
+    let mut log = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(ARCHIVE_LOG);
+
+    for action in actions {
+        let result = match &action {
+            ArchiveAction::Move(from, to) => move_file_to_subdir(from, to).map_err(|e| e.to_string()),
+            ArchiveAction::Zip(dir, zip_path) => zip_dir(dir, zip_path).map_err(|e| e.to_string()),
+        };
+
+        let line = match result {
+            Ok(_) => format!("{} {}", Local::now().format("%F %T"), action),
+            Err(e) => format!("{} ERR: {} - {}", Local::now().format("%F %T"), action, e),
+        };
+
+        if let Ok(log) = &mut log {
+            let _ = writeln!(log, "{line}");
+        }
+        messages.write().unwrap().push(line);
+    }
+}
+
+pub struct ArchiveWindow {
+    enabled: bool,
+    products: Vec<(String, PathBuf)>,
+    selected_product: usize,
+    settings: ArchiveSettings,
+
+    running: Arc<RwLock<bool>>,
+    messages: Arc<RwLock<Vec<String>>>,
+}
+
+impl ArchiveWindow {
+    pub fn default(products: Vec<(String, PathBuf)>) -> Self {
+        ArchiveWindow {
+            enabled: false,
+            products,
+            selected_product: 0,
+            settings: ArchiveSettings::default(),
+
+            running: Arc::new(RwLock::new(false)),
+            messages: Arc::new(RwLock::new(Vec::new())),
+        }
+    }
+
+    pub fn enable(&mut self) {
+        self.enabled = true;
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn start(&mut self, dry_run: bool) {
+        let Some((_, path)) = self.products.get(self.selected_product) else {
+            return;
+        };
+
+        self.messages.write().unwrap().clear();
+        *self.running.write().unwrap() = true;
+
+        let path = path.clone();
+        let settings = self.settings;
+        let running_lock = self.running.clone();
+        let message_lock = self.messages.clone();
+
+        thread::spawn(move || {
+            match plan_archive(&path, &settings) {
+                Ok(actions) if actions.is_empty() => {
+                    message_lock.write().unwrap().push("Nothing to archive.".to_string());
+                }
+                Ok(actions) if dry_run => {
+                    let mut messages = message_lock.write().unwrap();
+                    messages.push(format!("DRY RUN, {} actions:", actions.len()));
+                    messages.extend(actions.iter().map(|a| a.to_string()));
+                }
+                Ok(actions) => {
+                    execute_archive(actions, message_lock.clone());
+                }
+                Err(e) => {
+                    message_lock
+                        .write()
+                        .unwrap()
+                        .push(format!("ERR: Failed to read directory! {e}"));
+                }
+            }
+
+            *running_lock.write().unwrap() = false;
+        });
+    }
+
+    pub fn update(&mut self, ctx: &egui::Context) {
+        ctx.show_viewport_immediate(
+            egui::ViewportId::from_hash_of("ArchiveWindow"),
+            egui::ViewportBuilder::default()
+                .with_title("Archive")
+                .with_inner_size([600.0, 400.0]),
+            |ctx, class| {
+                assert!(
+                    class == egui::ViewportClass::Immediate,
+                    "This egui backend doesn't support multiple viewports"
+                );
+
+                egui::TopBottomPanel::top("Top").show(ctx, |ui| {
+                    let running = *self.running.read().unwrap();
+                    ui.set_enabled(!running);
+
+                    ui.horizontal(|ui| {
+                        egui::ComboBox::from_id_source("Archive product")
+                            .width(200.0)
+                            .selected_text(match self.products.get(self.selected_product) {
+                                Some(sel) => sel.0.clone(),
+                                None => "".to_string(),
+                            })
+                            .show_ui(ui, |ui| {
+                                for (i, t) in self.products.iter().enumerate() {
+                                    ui.selectable_value(&mut self.selected_product, i, t.0.clone());
+                                }
+                            });
+
+                        ui.label("Older than (h):");
+                        ui.add(
+                            egui::DragValue::new(&mut self.settings.age_limit)
+                                .speed(1.0)
+                                .clamp_range(1..=24 * 365),
+                        );
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Layout:");
+                        ui.selectable_value(&mut self.settings.layout, ArchiveLayout::Day, "Day");
+                        ui.selectable_value(&mut self.settings.layout, ArchiveLayout::Week, "Week");
+                        ui.selectable_value(&mut self.settings.layout, ArchiveLayout::Month, "Month");
+
+                        ui.checkbox(&mut self.settings.zip, "Zip finished folders")
+                            .on_hover_text("Zipped logs can't be loaded by the program anymore!");
+                    });
+
+                    ui.horizontal(|ui| {
+                        if ui.button("Preview").clicked() {
+                            self.start(true);
+                        }
+
+                        if ui.button("Archive!").clicked() {
+                            self.start(false);
+                        }
+
+                        if running {
+                            ui.spinner();
+                        }
+                    });
+                });
+
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    ui.spacing_mut().scroll = egui::style::ScrollStyle::solid();
+                    egui::ScrollArea::both()
+                        .auto_shrink(false)
+                        .show(ui, |ui| {
+                            for line in self.messages.read().unwrap().iter() {
+                                ui.monospace(line);
+                            }
+                        });
+                });
+
+                if *self.running.read().unwrap() {
+                    ctx.request_repaint_after(std::time::Duration::from_millis(200));
+                }
+
+                if ctx.input(|i| i.viewport().close_requested()) {
+                    self.enabled = false;
+                }
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ICT_archive_{}_{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn touch(path: &Path, age_hours: u64) {
+        let file = fs::File::create(path).unwrap();
+        let t = SystemTime::now() - std::time::Duration::from_secs(age_hours * 3600);
+        file.set_modified(t).unwrap();
+    }
+
+    #[test]
+    fn archive_dir_names() {
+        let day = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        assert_eq!(parse_archive_dir("2024_06_01"), Some((day, day)));
+
+        let monday = NaiveDate::from_ymd_opt(2024, 5, 27).unwrap();
+        let sunday = NaiveDate::from_ymd_opt(2024, 6, 2).unwrap();
+        assert_eq!(parse_archive_dir("2024_W22"), Some((monday, sunday)));
+
+        let last = NaiveDate::from_ymd_opt(2024, 12, 31).unwrap();
+        let first = NaiveDate::from_ymd_opt(2024, 12, 1).unwrap();
+        assert_eq!(parse_archive_dir("2024_12"), Some((first, last)));
+
+        assert_eq!(parse_archive_dir("misc"), None);
+    }
+
+    #[test]
+    fn plan_moves_old_logs_and_zips_finished_dirs() {
+        let base = test_dir("plan");
+        touch(&base.join("old"), 48);
+        touch(&base.join("new"), 0);
+        touch(&base.join("old.txt"), 48);
+        fs::create_dir(base.join("2020_01_01")).unwrap();
+        fs::create_dir(base.join("misc")).unwrap();
+
+        let settings = ArchiveSettings {
+            zip: true,
+            ..ArchiveSettings::default()
+        };
+        let mut actions: Vec<String> = plan_archive(&base, &settings)
+            .unwrap()
+            .iter()
+            .map(|a| a.to_string())
+            .collect();
+        actions.sort();
+
+        let old = Local::now() - Duration::try_hours(48).unwrap();
+        let day = base.join(ArchiveLayout::Day.dir_name(old));
+        let mut expected = vec![
+            ArchiveAction::Move(base.join("old"), day.join("old")).to_string(),
+            ArchiveAction::Zip(base.join("2020_01_01"), base.join("2020_01_01.zip")).to_string(),
+            ArchiveAction::Zip(day.clone(), day.with_extension("zip")).to_string(),
+        ];
+        expected.sort();
+
+        assert_eq!(actions, expected);
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn zip_keeps_subfolders_and_removes_the_dir() {
+        let base = test_dir("zip");
+        let dir = base.join("2020_01_01");
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("log1"), "log 1").unwrap();
+        fs::write(dir.join("sub").join("log2"), "log 2").unwrap();
+
+        let zip_path = dir.with_extension("zip");
+        zip_dir(&dir, &zip_path).unwrap();
+
+        assert!(!dir.exists());
+        let mut archive = zip::ZipArchive::new(fs::File::open(&zip_path).unwrap()).unwrap();
+        assert_eq!(archive.len(), 3);
+        let mut content = String::new();
+        std::io::Read::read_to_string(&mut archive.by_name("sub/log2").unwrap(), &mut content)
+            .unwrap();
+        assert_eq!(content, "log 2");
+
+        fs::remove_dir_all(&base).unwrap();
+    }
+}
@@ -0,0 +1,336 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use egui::Color32;
+use egui_extras::{Column, TableBuilder};
+
+use crate::{BResult, LogFile, LogFileHandler, TLimit, TType};
+
+/*
+Structured board report: the tests of one attempt as a table, colored by the result.
+The attempts are copied out of the LogFileHandler, so the window doesn't have to hold its lock.
+Two attempts can be compared side by side, aligned by the test names.
+*/
+
+// Values are different if they differ by more than this part of the limit range
+const VALUE_TOLERANCE: f32 = 0.1;
+
+pub struct TestRow {
+    pub name: String,
+    pub ttype: TType,
+    pub result: BResult,
+    pub value: f32,
+    pub limits: TLimit,
+}
+
+pub struct Attempt {
+    pub DMC: String,
+    pub DMC_mb: String,
+    pub source: PathBuf,
+    pub index: usize,
+    pub result: BResult,
+    pub time_start: u64,
+    pub time_end: u64,
+    pub tests: Vec<TestRow>,
+}
+
+impl Attempt {
+    pub fn new(log: &LogFile) -> Self {
+        Attempt {
+            DMC: log.get_DMC().to_string(),
+            DMC_mb: log.get_DMC_mb().to_string(),
+            source: log.get_source().clone(),
+            index: log.get_index(),
+            result: log.get_result(),
+            time_start: log.get_time_start(),
+            time_end: log.get_time_end(),
+            tests: log
+                .get_tests()
+                .iter()
+                .map(|t| TestRow {
+                    name: t.get_name().to_string(),
+                    ttype: t.get_ttype(),
+                    result: t.get_result().0,
+                    value: t.get_result().1,
+                    limits: t.get_limits(),
+                })
+                .collect(),
+        }
+    }
+}
+
+// Every attempt of 'DMC', ordered by time
+pub fn attempts_of(lfh: &LogFileHandler, DMC: &str) -> Vec<Attempt> {
+    let mut logs: Vec<&LogFile> = lfh
+        .get_logs()
+        .into_iter()
+        .filter(|l| l.get_DMC() == DMC)
+        .collect();
+    logs.sort_by_key(|l| l.get_time_start());
+
+    logs.into_iter().map(Attempt::new).collect()
+}
+
+// DMCs of the boards on the panel 'DMC_mb', ordered by their index
+pub fn boards_of_panel(lfh: &LogFileHandler, DMC_mb: &str) -> Vec<String> {
+    let mut boards: Vec<(usize, String)> = lfh
+        .get_logs()
+        .into_iter()
+        .filter(|l| l.get_DMC_mb() == DMC_mb || l.get_DMC() == DMC_mb)
+        .map(|l| (l.get_index(), l.get_DMC().to_string()))
+        .collect();
+    boards.sort();
+    boards.dedup();
+
+    boards.into_iter().map(|b| b.1).collect()
+}
+
+// DMC of the board on 'index' of the panel 'DMC_mb'
+pub fn board_at_index(lfh: &LogFileHandler, DMC_mb: &str, index: usize) -> Option<String> {
+    lfh.get_logs()
+        .into_iter()
+        .find(|l| (l.get_DMC_mb() == DMC_mb || l.get_DMC() == DMC_mb) && l.get_index() == index)
+        .map(|l| l.get_DMC().to_string())
+}
+
+pub fn format_value(value: f32, ttype: &TType) -> String {
+    format!("{:.4E} {}", value, ttype.unit())
+}
+
+pub fn format_limits(limits: &TLimit, ttype: &TType) -> String {
+    match limits {
+        TLimit::Lim3(nom, ul, ll) => format!(
+            "{:.4E} .. {:.4E} (nom: {:.4E}) {}",
+            ll,
+            ul,
+            nom,
+            ttype.unit()
+        ),
+        TLimit::Lim2(ul, ll) => format!("{:.4E} .. {:.4E} {}", ll, ul, ttype.unit()),
+        TLimit::None => "-".to_string(),
+    }
+}
+
+pub struct ReportFilter {
+    pub failed_only: bool,
+    pub search: String,
+}
+
+impl ReportFilter {
+    pub fn default() -> Self {
+        ReportFilter {
+            failed_only: false,
+            search: String::new(),
+        }
+    }
+
+    fn matches_name(&self, name: &str) -> bool {
+        self.search.is_empty() || name.to_lowercase().contains(&self.search.to_lowercase())
+    }
+
+    pub fn matches(&self, test: &TestRow) -> bool {
+        (!self.failed_only || test.result == BResult::Fail) && self.matches_name(&test.name)
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.failed_only, "Failed only");
+        ui.label("Test:");
+        ui.add(egui::TextEdit::singleline(&mut self.search).desired_width(150.0));
+    }
+}
+
+pub fn report_table(ui: &mut egui::Ui, attempt: &Attempt, filter: &ReportFilter) {
+    let text_color = ui.visuals().text_color();
+
+    TableBuilder::new(ui)
+        .striped(true)
+        .column(Column::initial(150.0).resizable(true))
+        .column(Column::initial(70.0).resizable(true))
+        .column(Column::initial(120.0).resizable(true))
+        .column(Column::initial(250.0).resizable(true))
+        .column(Column::remainder())
+        .header(20.0, |mut header| {
+            for name in ["Test", "Type", "Value", "Limits", "Result"] {
+                header.col(|ui| {
+                    ui.strong(name);
+                });
+            }
+        })
+        .body(|mut body| {
+            for test in attempt.tests.iter().filter(|t| filter.matches(t)) {
+                let color = if test.result == BResult::Fail {
+                    Color32::RED
+                } else {
+                    text_color
+                };
+
+                body.row(16.0, |mut row| {
+                    row.col(|ui| {
+                        ui.colored_label(color, &test.name);
+                    });
+                    row.col(|ui| {
+                        ui.label(test.ttype.print());
+                    });
+                    row.col(|ui| {
+                        ui.colored_label(color, format_value(test.value, &test.ttype));
+                    });
+                    row.col(|ui| {
+                        ui.label(format_limits(&test.limits, &test.ttype));
+                    });
+                    row.col(|ui| {
+                        ui.colored_label(test.result.into_dark_color(), test.result.print());
+                    });
+                });
+            }
+        });
+}
+
+fn limit_range(test: &TestRow) -> Option<f32> {
+    match test.limits {
+        TLimit::Lim3(_, ul, ll) | TLimit::Lim2(ul, ll) => Some((ul - ll).abs()),
+        TLimit::None => None,
+    }
+}
+
+// Compared to the wider limit range of the two, if the limits changed.
+// Tests without limits are never marked, there is nothing to scale the difference with.
+fn value_differs(a: &TestRow, b: &TestRow) -> bool {
+    let range = match (limit_range(a), limit_range(b)) {
+        (Some(x), Some(y)) => x.max(y),
+        (Some(x), None) | (None, Some(x)) => x,
+        (None, None) => return false,
+    };
+    (a.value - b.value).abs() > range * VALUE_TOLERANCE
+}
+
+// A row of the comparison: the test in the two attempts, missing if it wasn't run
+type CompareRow<'a> = (&'a str, Option<&'a TestRow>, Option<&'a TestRow>);
+
+fn compare_rows<'a>(a: &'a Attempt, b: &'a Attempt) -> Vec<CompareRow<'a>> {
+    let tests_b: HashMap<&str, &TestRow> = b.tests.iter().map(|t| (t.name.as_str(), t)).collect();
+    let tests_a: HashMap<&str, &TestRow> = a.tests.iter().map(|t| (t.name.as_str(), t)).collect();
+
+    // In the order of the first attempt, then the tests only in the second one
+    let mut rows: Vec<CompareRow> = a
+        .tests
+        .iter()
+        .map(|t| {
+            (
+                t.name.as_str(),
+                Some(t),
+                tests_b.get(t.name.as_str()).copied(),
+            )
+        })
+        .collect();
+    rows.extend(
+        b.tests
+            .iter()
+            .filter(|t| !tests_a.contains_key(t.name.as_str()))
+            .map(|t| (t.name.as_str(), None, Some(t))),
+    );
+
+    rows
+}
+
+pub fn compare_table(
+    ui: &mut egui::Ui,
+    a: &Attempt,
+    b: &Attempt,
+    filter: &ReportFilter,
+    differences_only: bool,
+) {
+    let text_color = ui.visuals().text_color();
+    let result_bg = Color32::RED.gamma_multiply(0.3);
+    let value_bg = Color32::GOLD.gamma_multiply(0.3);
+
+    let rows: Vec<(CompareRow, bool, bool)> = compare_rows(a, b)
+        .into_iter()
+        .filter_map(|row| {
+            let (name, ta, tb) = row;
+            let result_diff = ta.map(|t| t.result) != tb.map(|t| t.result);
+            let value_diff = match (ta, tb) {
+                (Some(ta), Some(tb)) => value_differs(ta, tb),
+                _ => false,
+            };
+            let failed = [ta, tb].iter().flatten().any(|t| t.result == BResult::Fail);
+
+            (filter.matches_name(name)
+                && (!filter.failed_only || failed)
+                && (!differences_only || result_diff || value_diff))
+                .then_some((row, result_diff, value_diff))
+        })
+        .collect();
+
+    TableBuilder::new(ui)
+        .striped(true)
+        .column(Column::initial(150.0).resizable(true))
+        .columns(Column::initial(120.0).resizable(true), 2)
+        .column(Column::initial(100.0).resizable(true))
+        .columns(Column::initial(60.0), 2)
+        .column(Column::remainder())
+        .header(20.0, |mut header| {
+            for name in [
+                "Test",
+                "Value A",
+                "Value B",
+                "Difference",
+                "A",
+                "B",
+                "Limits",
+            ] {
+                header.col(|ui| {
+                    ui.strong(name);
+                });
+            }
+        })
+        .body(|mut body| {
+            for ((name, ta, tb), result_diff, value_diff) in rows {
+                let color = if result_diff {
+                    Color32::RED
+                } else {
+                    text_color
+                };
+                let ttype = ta.or(tb).map(|t| &t.ttype);
+
+                body.row(16.0, |mut row| {
+                    row.col(|ui| {
+                        ui.colored_label(color, name);
+                    });
+                    for t in [ta, tb] {
+                        row.col(|ui| {
+                            if value_diff {
+                                ui.painter().rect_filled(ui.max_rect(), 0.0, value_bg);
+                            }
+                            if let Some(t) = t {
+                                ui.label(format_value(t.value, &t.ttype));
+                            } else {
+                                ui.label("-");
+                            }
+                        });
+                    }
+                    row.col(|ui| {
+                        if let (Some(ta), Some(tb)) = (ta, tb) {
+                            ui.label(format_value(tb.value - ta.value, &ta.ttype));
+                        }
+                    });
+                    for t in [ta, tb] {
+                        row.col(|ui| {
+                            if result_diff {
+                                ui.painter().rect_filled(ui.max_rect(), 0.0, result_bg);
+                            }
+                            if let Some(t) = t {
+                                ui.colored_label(t.result.into_dark_color(), t.result.print());
+                            } else {
+                                ui.label("-");
+                            }
+                        });
+                    }
+                    row.col(|ui| {
+                        if let (Some(t), Some(ttype)) = (ta.or(tb), ttype) {
+                            ui.label(format_limits(&t.limits, ttype));
+                        }
+                    });
+                });
+            }
+        });
+}
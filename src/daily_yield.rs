@@ -1,10 +1,13 @@
-use chrono::{DateTime, Local, NaiveDate, NaiveTime};
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime};
 use std::{
-    fs, path::{Path, PathBuf}, sync::{Arc, Mutex}, thread
+    collections::HashMap, fs, io::{BufWriter, Write}, path::{Path, PathBuf}, sync::{Arc, Mutex}, thread
 };
 use rust_xlsxwriter::*;
 
-use crate::LogFileHandler;
+use crate::{
+    export_long, parse_archive_dir, u64_to_datetime, BResult, ExportFormat, ExportSettings,
+    LogFileHandler, ShiftModel,
+};
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 fn u64_to_hours(mut x: u64) -> String {
@@ -18,10 +21,8 @@ fn u64_to_hours(mut x: u64) -> String {
 
 
 fn is_dir_in_t(s: &Path, start: DateTime<Local>, end: DateTime<Local>) -> bool {
-    if let Ok(as_time) =
-        NaiveDate::parse_from_str(s.file_name().unwrap().to_str().unwrap(), "%Y_%m_%d")
-    {
-        if start.date_naive() <= as_time && end.date_naive() >= as_time {
+    if let Some((first, last)) = parse_archive_dir(s.file_name().unwrap().to_str().unwrap()) {
+        if start.date_naive() <= last && end.date_naive() >= first {
             return true;
         }
     }
@@ -79,6 +80,209 @@ fn write_header(worksheet: &mut Worksheet, date: NaiveDate) -> Result<u32, XlsxE
     Ok(3)
 }
 
+const TOP_FAILURES: usize = 5;
+
+// Subtotals of one product in one shift
+struct ShiftStats {
+    name: String,
+    first_pass: (u32, u32),   // (OK, NOK) after the first test
+    after_retest: (u32, u32), // (OK, NOK) after retests
+    top_failures: Vec<(String, u32)>,
+}
+
+// The shift subtotals are taken from the logs already loaded for the whole day.
+// Each board belongs to the shift of its first test. Its retests are counted in the same shift,
+// even if they were done in the next one, so the first pass yield only counts first tests.
+fn get_shift_stats(lfh: &LogFileHandler, shifts: &[(DateTime<Local>, DateTime<Local>)]) -> Vec<ShiftStats> {
+    let attempts: Vec<(NaiveDateTime, &str, BResult, Vec<String>)> = lfh
+        .get_logs()
+        .into_iter()
+        .filter_map(|l| {
+            let t = u64_to_datetime(l.get_time_start())?;
+            Some((t, l.get_DMC(), l.get_result(), l.get_failed_tests()))
+        })
+        .collect();
+
+    let shifts: Vec<(String, NaiveDateTime, NaiveDateTime)> = shifts
+        .iter()
+        .map(|(s, e)| {
+            (format!("{} - {}", s.format("%R"), e.format("%R")), s.naive_local(), e.naive_local())
+        })
+        .collect();
+
+    shift_stats(attempts, &shifts)
+}
+
+// 'attempts' are (start of the test, DMC, result, failed tests), 'shifts' are (name, start, end)
+fn shift_stats(
+    mut attempts: Vec<(NaiveDateTime, &str, BResult, Vec<String>)>,
+    shifts: &[(String, NaiveDateTime, NaiveDateTime)],
+) -> Vec<ShiftStats> {
+    attempts.sort_by_key(|a| a.0);
+
+    // DMC -> (shift of the first test, result of the first test, result of the last test)
+    let mut boards: HashMap<&str, (usize, BResult, BResult)> = HashMap::new();
+    let mut failures: Vec<HashMap<String, u32>> = vec![HashMap::new(); shifts.len()];
+
+    for (t, DMC, result, failed) in attempts {
+        let shift = match boards.get_mut(DMC) {
+            Some(board) => {
+                board.2 = result;
+                board.0
+            }
+            None => {
+                let Some(shift) = shifts.iter().position(|s| s.1 <= t && t < s.2) else {
+                    continue;
+                };
+                boards.insert(DMC, (shift, result, result));
+                shift
+            }
+        };
+
+        for test in failed {
+            *failures[shift].entry(test).or_default() += 1;
+        }
+    }
+
+    shifts
+        .iter()
+        .zip(failures)
+        .enumerate()
+        .map(|(i, (shift, failures))| {
+            let boards: Vec<&(usize, BResult, BResult)> =
+                boards.values().filter(|b| b.0 == i).collect();
+            let ok_nok = |passed: usize| (passed as u32, (boards.len() - passed) as u32);
+            let first_pass = boards.iter().filter(|b| b.1 == BResult::Pass).count();
+            let after_retest = boards.iter().filter(|b| b.2 == BResult::Pass).count();
+
+            let mut top_failures: Vec<(String, u32)> = failures.into_iter().collect();
+            top_failures.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            top_failures.truncate(TOP_FAILURES);
+
+            ShiftStats {
+                name: shift.0.clone(),
+                first_pass: ok_nok(first_pass),
+                after_retest: ok_nok(after_retest),
+                top_failures,
+            }
+        })
+        .collect()
+}
+
+fn write_yield(worksheet: &mut Worksheet, row: u32, col: u16, ok_nok: (u32, u32)) -> Result<(), XlsxError> {
+    let format = Format::new().set_num_format("0.00%");
+
+    if ok_nok.0 + ok_nok.1 > 0 {
+        let ratio = ok_nok.0 as f64 / (ok_nok.0 + ok_nok.1) as f64;
+        worksheet.write_number_with_format(row, col, ratio, &format)?;
+    }
+
+    Ok(())
+}
+
+fn write_shifts(worksheet: &mut Worksheet, shifts: &[ShiftStats], mut row: u32) -> Result<u32, XlsxError> {
+    let header_format = Format::new().set_border_bottom(FormatBorder::Thin);
+
+    worksheet.write_with_format(row, 1, "Shift", &header_format)?;
+    worksheet.write_with_format(row, 2, "Boards", &header_format)?;
+    worksheet.write_with_format(row, 3, "First pass", &header_format)?;
+    worksheet.write_with_format(row, 4, "After retest", &header_format)?;
+    row += 1;
+
+    for shift in shifts {
+        worksheet.write(row, 1, &shift.name)?;
+        worksheet.write(row, 2, shift.first_pass.0 + shift.first_pass.1)?;
+        write_yield(worksheet, row, 3, shift.first_pass)?;
+        write_yield(worksheet, row, 4, shift.after_retest)?;
+        row += 1;
+    }
+    row += 2;
+
+    for shift in shifts.iter().filter(|s| !s.top_failures.is_empty()) {
+        worksheet.write_with_format(row, 1, &shift.name, &header_format)?;
+        worksheet.write_with_format(row, 2, "Top failures", &header_format)?;
+        row += 1;
+
+        for (name, total) in &shift.top_failures {
+            worksheet.write(row, 1, name)?;
+            worksheet.write(row, 2, *total)?;
+            row += 1;
+        }
+        row += 1;
+    }
+    row += 2;
+
+    Ok(row)
+}
+
+// Summary worksheet, comparing the shifts across all the products
+fn write_shift_summary(
+    worksheet: &mut Worksheet,
+    date: NaiveDate,
+    products: &[(String, Vec<ShiftStats>)],
+) -> Result<(), XlsxError> {
+    let date_format = Format::new().set_num_format("yyyy-mm-dd");
+    let header_format = Format::new().set_border_bottom(FormatBorder::Thin);
+    let footer_format = Format::new().set_bold().set_border_top(FormatBorder::Thin);
+    let footer_yield_format = Format::new()
+        .set_bold()
+        .set_border_top(FormatBorder::Thin)
+        .set_num_format("0.00%");
+
+    worksheet.set_column_width_pixels(0, 120)?;
+    worksheet.set_column_width_pixels(1, 200)?;
+    worksheet.set_column_width_pixels(2, 100)?;
+    worksheet.set_column_width_pixels(3, 100)?;
+    worksheet.set_column_width_pixels(4, 100)?;
+
+    worksheet.write(0, 0, "day reported:")?;
+    worksheet.write_datetime_with_format(0, 1, date, &date_format)?;
+
+    let mut row = 2;
+    worksheet.write_with_format(row, 0, "Shift", &header_format)?;
+    worksheet.write_with_format(row, 1, "Product", &header_format)?;
+    worksheet.write_with_format(row, 2, "Boards", &header_format)?;
+    worksheet.write_with_format(row, 3, "First pass", &header_format)?;
+    worksheet.write_with_format(row, 4, "After retest", &header_format)?;
+    row += 1;
+
+    let shift_count = products.iter().map(|p| p.1.len()).max().unwrap_or_default();
+    for i in 0..shift_count {
+        let mut first_pass = (0, 0);
+        let mut after_retest = (0, 0);
+
+        for (product, shifts) in products {
+            if let Some(shift) = shifts.get(i) {
+                worksheet.write(row, 0, &shift.name)?;
+                worksheet.write(row, 1, product)?;
+                worksheet.write(row, 2, shift.first_pass.0 + shift.first_pass.1)?;
+                write_yield(worksheet, row, 3, shift.first_pass)?;
+                write_yield(worksheet, row, 4, shift.after_retest)?;
+
+                first_pass.0 += shift.first_pass.0;
+                first_pass.1 += shift.first_pass.1;
+                after_retest.0 += shift.after_retest.0;
+                after_retest.1 += shift.after_retest.1;
+                row += 1;
+            }
+        }
+
+        worksheet.write_blank(row, 0, &footer_format)?;
+        worksheet.write_with_format(row, 1, "All products", &footer_format)?;
+        worksheet.write_with_format(row, 2, first_pass.0 + first_pass.1, &footer_format)?;
+        worksheet.write_blank(row, 3, &footer_yield_format)?;
+        worksheet.write_blank(row, 4, &footer_yield_format)?;
+        if first_pass.0 + first_pass.1 > 0 {
+            let ratio = |x: (u32, u32)| x.0 as f64 / (x.0 + x.1) as f64;
+            worksheet.write_number_with_format(row, 3, ratio(first_pass), &footer_yield_format)?;
+            worksheet.write_number_with_format(row, 4, ratio(after_retest), &footer_yield_format)?;
+        }
+        row += 2;
+    }
+
+    Ok(())
+}
+
 fn write_product(worksheet: &mut Worksheet, lfh: LogFileHandler, mut row: u32) -> Result<u32, XlsxError> {
     let merge_format = Format::new().set_bold().set_border_bottom(FormatBorder::Medium);
     let header_format = Format::new().set_border_bottom(FormatBorder::Thin);
@@ -167,24 +371,175 @@ fn write_product(worksheet: &mut Worksheet, lfh: LogFileHandler, mut row: u32) -
 
     Ok(row.max(row_b))
 }
+
+// Configured times can fall into a DST change. In the overlap the earlier one is taken,
+// a time skipped by the change is moved forward by an hour.
+fn to_local(t: NaiveDateTime) -> Option<DateTime<Local>> {
+    t.and_local_timezone(Local)
+        .earliest()
+        .or_else(|| (t + Duration::try_hours(1)?).and_local_timezone(Local).earliest())
+}
+
+// Generates the daily report for the production day starting on 'date', as defined by the shift model.
+// The report is saved as xlsx to 'out_path'. With a 'long_format', the measurements of the day are
+// also exported next to it, with the extension of the format.
+// Progress and errors are reported through 'output'. Returns false if an output could not be saved.
+pub fn generate_daily_report(
+    date: NaiveDate,
+    paths: &[PathBuf],
+    shifts: &ShiftModel,
+    out_path: &str,
+    long_format: Option<ExportFormat>,
+    mut output: impl FnMut(&str),
+) -> bool {
+    let (start_t, end_t) = shifts.production_day(date);
+    let (start_t, end_t) = match (to_local(start_t), to_local(end_t)) {
+        (Some(s), Some(e)) => (s, e),
+        _ => {
+            output(&format!("ERR: Invalid local time for the production day: {} \n", date));
+            return false;
+        }
+    };
+    let mut shift_times: Vec<(DateTime<Local>, DateTime<Local>)> = Vec::new();
+    for (s, e) in shifts.shifts_of_day(date) {
+        match (to_local(s), to_local(e)) {
+            (Some(s), Some(e)) => shift_times.push((s, e)),
+            _ => {
+                output(&format!("ERR: Invalid local time for the shift starting at: {} \n", s));
+                return false;
+            }
+        }
+    }
+    let mut shift_summary: Vec<(String, Vec<ShiftStats>)> = Vec::new();
+
+    let report_path = Path::new(out_path).with_extension("xlsx");
+    let mut long_out = None;
+    let mut header_written = false;
+    if let Some(format) = long_format {
+        match fs::File::create(report_path.with_extension(format.extension())) {
+            Ok(x) => long_out = Some((format, BufWriter::new(x))),
+            Err(x) => {
+                output(&format!("ERR: Failed to create output:\n {} \n", x));
+                return false;
+            }
+        }
+    }
+
+    output("Starting report generation...\n");
+
+    let mut output_row: u32 = 0;
+    let mut workbook = Workbook::new();
+    let worksheet = workbook.add_worksheet();
+
+    match write_header(worksheet, start_t.date_naive()) {
+        Ok(x) => {
+            output_row = x;
+        }
+        Err(x) => {
+            output(&format!("ERR: Failed to write header:\n {} \n", x));
+        }
+    }
+
+    for path in paths {
+        output(&format!("Scanning directory: {}\n", path.to_string_lossy()));
+
+        if path.exists() {
+            if let Ok(logs) = get_logs_in_path_t(path, start_t, end_t) {
+                output(&format!("\tFound {} logs.\n", logs.len()));
+
+                let mut lfh = LogFileHandler::new();
+                for (log, _) in logs {
+                    lfh.push_from_file(&log);
+                }
+                lfh.update();
+
+                if let Some((format, out)) = &mut long_out {
+                    let settings = ExportSettings::default();
+                    match export_long(&lfh, &settings, *format, out, !header_written) {
+                        Ok(x) => {
+                            output(&format!("\tExported {} measurements.\n", x));
+                            header_written = true;
+                        }
+                        Err(x) => {
+                            output(&format!("ERR: Failed to export product:\n {} \n", x));
+                        }
+                    }
+                }
+
+                if !lfh.is_empty() {
+                    let product = lfh.get_product_id();
+                    let shift_stats = get_shift_stats(&lfh, &shift_times);
+
+                    match write_product(worksheet, lfh, output_row)
+                        .and_then(|x| write_shifts(worksheet, &shift_stats, x))
+                    {
+                        Ok(x) => {
+                            output_row = x;
+                        }
+                        Err(x) => {
+                            output(&format!("ERR: Failed to write product:\n {} \n", x));
+                        }
+                    }
+
+                    shift_summary.push((product, shift_stats));
+                }
+            } else {
+                output("\tERR: Failed to read directory!\n");
+            }
+        } else {
+            output("\tERR: Directory not found!\n");
+        }
+    }
+
+    let mut ok = true;
+    if let Some((_, mut out)) = long_out {
+        if let Err(x) = out.flush() {
+            output(&format!("ERR: Failed to write measurements:\n {} \n", x));
+            ok = false;
+        }
+    }
+
+    let summary_sheet = workbook.add_worksheet();
+    if let Err(x) = summary_sheet
+        .set_name("Shifts")
+        .and_then(|ws| write_shift_summary(ws, start_t.date_naive(), &shift_summary))
+    {
+        output(&format!("ERR: Failed to write shift summary:\n {} \n", x));
+    }
+
+    if let Err(x) = workbook.save(&report_path) {
+        output(&format!("ERR: Failed to write output:\n {} \n", x));
+        false
+    } else if ok {
+        output("Writing output is succesfull!\n");
+        true
+    } else {
+        false
+    }
+}
+
 pub struct DailyYieldWindow {
     enabled: bool,
     running: Arc<Mutex<bool>>,
     date: NaiveDate,
     out_path: String,
+    long_format: Option<ExportFormat>, // measurements exported next to the report
     output_message: Arc<Mutex<String>>,
     path_list: Vec<PathBuf>,
+    shift_model: ShiftModel,
 }
 
 impl DailyYieldWindow {
-    pub fn default(path_list: Vec<PathBuf>) -> Self {
+    pub fn default(path_list: Vec<PathBuf>, shift_model: ShiftModel) -> Self {
         DailyYieldWindow {
             enabled: false,
             running: Arc::new(Mutex::new(false)),
             date: Local::now().date_naive().pred_opt().unwrap(),
             out_path: ".\\out.xlsx".to_string(),
+            long_format: None,
             output_message: Arc::new(Mutex::new(String::new())),
             path_list,
+            shift_model,
         }
     }
 
@@ -200,107 +555,19 @@ impl DailyYieldWindow {
         let running_lock = self.running.clone();
         let output_lock = self.output_message.clone();
         let paths = self.path_list.clone();
-        let start_t = self
-            .date
-            .and_time(NaiveTime::from_hms_opt(6, 0, 0).unwrap())
-            .and_local_timezone(Local)
-            .unwrap();
-        let end_t = self
-            .date
-            .succ_opt()
-            .unwrap()
-            .and_time(NaiveTime::from_hms_opt(6, 0, 0).unwrap())
-            .and_local_timezone(Local)
-            .unwrap();
+        let shifts = self.shift_model.clone();
+        let date = self.date;
         let context = ctx.clone();
         let out_path = self.out_path.clone();
+        let long_format = self.long_format;
 
         thread::spawn(move || {
             *running_lock.lock().unwrap() = true;
-            output_lock
-                .lock()
-                .unwrap()
-                .push_str("Starting report generation...\n");
-            context.request_repaint();
 
-            let mut output_row: u32 = 0;
-            let mut workbook = Workbook::new();
-            let worksheet = workbook.add_worksheet();
-
-            match write_header(worksheet, start_t.date_naive()) {
-                Ok(x) => {
-                    output_row = x;
-                },
-                Err(x) => {
-                    output_lock
-                    .lock()
-                    .unwrap()
-                    .push_str(&format!("ERR: Failed to write header:\n {} \n", x));
-                },
-            }
-
-            for path in paths {
-                output_lock
-                    .lock()
-                    .unwrap()
-                    .push_str(&format!("Scanning directory: {}\n", path.to_string_lossy()));
+            generate_daily_report(date, &paths, &shifts, &out_path, long_format, |msg| {
+                output_lock.lock().unwrap().push_str(msg);
                 context.request_repaint();
-
-                if path.exists() {
-                    if let Ok(logs) = get_logs_in_path_t(&path, start_t, end_t) {
-                        output_lock
-                            .lock()
-                            .unwrap()
-                            .push_str(&format!("\tFound {} logs.\n", logs.len()));
-                        context.request_repaint();
-
-                        let mut lfh = LogFileHandler::new();
-                        for (log, _) in logs {
-                            lfh.push_from_file(&log);
-                        }
-                        lfh.update();
-
-                        if !lfh.is_empty() {
-                            match write_product(worksheet, lfh, output_row) {
-                                Ok(x) => {
-                                    output_row = x;
-                                },
-                                Err(x) => {
-                                    output_lock
-                                    .lock()
-                                    .unwrap()
-                                    .push_str(&format!("ERR: Failed to write product:\n {} \n", x));
-                                },
-                            }
-                        }
-
-                    } else {
-                        output_lock
-                            .lock()
-                            .unwrap()
-                            .push_str("\tERR: Failed to read directory!\n");
-                        context.request_repaint();
-                    }
-                } else {
-                    output_lock
-                        .lock()
-                        .unwrap()
-                        .push_str("\tERR: Directory not found!\n");
-                    context.request_repaint();
-                }
-            }
-
-            if let Err(x) = workbook.save(out_path) {
-                output_lock
-                .lock()
-                .unwrap()
-                .push_str(&format!("ERR: Failed to write output:\n {} \n", x));
-            } else {
-                output_lock
-                .lock()
-                .unwrap()
-                .push_str("Writing output is succesfull!\n");
-            }
+            });
 
             *running_lock.lock().unwrap() = false;
             context.request_repaint();
@@ -337,11 +604,22 @@ impl DailyYieldWindow {
                         ui.text_edit_singleline(&mut self.out_path );
 
                         if ui.button("📁").clicked() {
-                            if let Some(input_path) = rfd::FileDialog::new().add_filter("xlsx", &["xlsx"]).save_file() {
+                            if let Some(input_path) = rfd::FileDialog::new()
+                                .add_filter("xlsx", &["xlsx"])
+                                .save_file() {
                                 self.out_path = input_path.to_string_lossy().to_string();
                             }
                         }
                     });
+
+                    ui.horizontal(|ui| {
+                        ui.monospace("Measurements: ");
+                        ui.selectable_value(&mut self.long_format, None, "-");
+                        ui.selectable_value(&mut self.long_format, Some(ExportFormat::Csv), "CSV");
+                        ui.selectable_value(&mut self.long_format, Some(ExportFormat::JsonLines), "JSON Lines");
+                    })
+                    .response
+                    .on_hover_text("Also exports the measurements of the day next to the report.");
                 });
 
                 egui::CentralPanel::default().show(ctx, |ui| {
@@ -364,3 +642,55 @@ impl DailyYieldWindow {
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn t(h: u32, m: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 3, 4)
+            .unwrap()
+            .and_hms_opt(h, m, 0)
+            .unwrap()
+    }
+
+    fn shifts() -> Vec<(String, NaiveDateTime, NaiveDateTime)> {
+        vec![
+            ("06:00 - 14:00".to_string(), t(6, 0), t(14, 0)),
+            ("14:00 - 22:00".to_string(), t(14, 0), t(22, 0)),
+        ]
+    }
+
+    #[test]
+    fn retest_in_the_next_shift_counts_in_the_first() {
+        let attempts = vec![
+            (t(14, 5), "B1", BResult::Pass, vec![]),
+            (t(13, 55), "B1", BResult::Fail, vec!["R12".to_string()]),
+        ];
+
+        let stats = shift_stats(attempts, &shifts());
+
+        assert_eq!(stats[0].first_pass, (0, 1));
+        assert_eq!(stats[0].after_retest, (1, 0));
+        assert_eq!(stats[0].top_failures, vec![("R12".to_string(), 1)]);
+        assert_eq!(stats[1].first_pass, (0, 0));
+        assert_eq!(stats[1].after_retest, (0, 0));
+        assert!(stats[1].top_failures.is_empty());
+    }
+
+    #[test]
+    fn boards_are_split_by_their_first_test() {
+        let attempts = vec![
+            (t(7, 0), "B1", BResult::Pass, vec![]),
+            (t(8, 0), "B2", BResult::Fail, vec!["C3".to_string()]),
+            (t(15, 0), "B3", BResult::Pass, vec![]),
+            (t(23, 0), "B4", BResult::Pass, vec![]),
+        ];
+
+        let stats = shift_stats(attempts, &shifts());
+
+        assert_eq!(stats[0].first_pass, (1, 1));
+        assert_eq!(stats[0].after_retest, (1, 1));
+        assert_eq!(stats[1].first_pass, (1, 0));
+    }
+}
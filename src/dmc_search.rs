@@ -0,0 +1,312 @@
+use std::fs;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::thread;
+
+use crate::{parse_archive_dir, LogFile};
+
+/*
+Global DMC search, for traceability when only the serial is known.
+It scans the log directory of every product, the archive subfolders in them, and the zipped archives.
+Parsing every log would be too slow, so the files are read line by line, and only the ones
+containing the DMC are parsed. Logs in a zip are extracted to a temporary file for parsing.
+Logfiles have no extension, every other file is skipped.
+*/
+
+pub struct DmcHit {
+    pub product: String,
+    pub location: PathBuf, // the logfile, or the zip and the name of the log in it
+    pub log: LogFile,
+}
+
+pub struct DmcSearch {
+    pub DMC: String,
+    pub running: Arc<RwLock<bool>>,
+    pub scanned: Arc<RwLock<usize>>,
+    pub hits: Arc<RwLock<Vec<DmcHit>>>, // ordered by the start of the test, when finished
+}
+
+enum LogSource {
+    File(PathBuf),
+    Zip(PathBuf),
+}
+
+fn is_archive_name(name: &str) -> bool {
+    parse_archive_dir(name).is_some()
+}
+
+// Logs in 'dir', and in the archive subfolders and zipped archives of it.
+// Unreadable entries are skipped, so one bad folder doesn't stop the search.
+fn collect_logs(dir: &Path, ret: &mut Vec<LogSource>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(x) => x,
+        Err(e) => {
+            println!("ERR: Failed to read directory {}: {e}", dir.display());
+            return;
+        }
+    };
+
+    for entry in entries {
+        let Ok(entry) = entry else {
+            continue;
+        };
+        let path = entry.path();
+
+        if path.is_dir() {
+            if path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(is_archive_name)
+            {
+                collect_logs(&path, ret);
+            }
+        } else if path.extension().is_none() {
+            ret.push(LogSource::File(path));
+        } else if path.extension() == Some(std::ffi::OsStr::new("zip"))
+            && path
+                .file_stem()
+                .and_then(|n| n.to_str())
+                .is_some_and(is_archive_name)
+        {
+            ret.push(LogSource::Zip(path));
+        }
+    }
+}
+
+// Reads 'reader' line by line, until a line containing 'needle'
+fn contains(reader: impl Read, needle: &[u8]) -> std::io::Result<bool> {
+    let mut reader = BufReader::new(reader);
+    let mut line = Vec::new();
+
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            return Ok(false);
+        }
+        if line.windows(needle.len()).any(|w| w == needle) {
+            return Ok(true);
+        }
+    }
+}
+
+// Parses the log, if it is a test of 'DMC'
+fn load_if_DMC(path: &Path, DMC: &str) -> Option<LogFile> {
+    match LogFile::load(path) {
+        Ok(log) if log.get_DMC() == DMC => Some(log),
+        Ok(_) => None,
+        Err(e) => {
+            println!("ERR: Failed to load {}: {e}", path.display());
+            None
+        }
+    }
+}
+
+fn search_zip(zip_path: &Path, DMC: &str, scanned: &RwLock<usize>) -> Vec<(PathBuf, LogFile)> {
+    let mut ret = Vec::new();
+
+    let archive = fs::File::open(zip_path)
+        .map_err(|e| e.to_string())
+        .and_then(|f| zip::ZipArchive::new(f).map_err(|e| e.to_string()));
+    let mut archive = match archive {
+        Ok(x) => x,
+        Err(e) => {
+            println!("ERR: Failed to open archive {}: {e}", zip_path.display());
+            return ret;
+        }
+    };
+
+    let tmp_path = std::env::temp_dir().join(format!("ICT_dmc_search_{}", std::process::id()));
+
+    for i in 0..archive.len() {
+        let name = match archive.by_index(i) {
+            Ok(entry) if entry.is_file() && Path::new(entry.name()).extension().is_none() => {
+                entry.name().to_string()
+            }
+            _ => continue,
+        };
+        *scanned.write().unwrap() += 1;
+
+        let found = archive
+            .by_index(i)
+            .map_err(std::io::Error::other)
+            .and_then(|entry| contains(entry, DMC.as_bytes()));
+        if !found.unwrap_or(false) {
+            continue;
+        }
+
+        let extracted = archive
+            .by_index(i)
+            .map_err(std::io::Error::other)
+            .and_then(|mut entry| std::io::copy(&mut entry, &mut fs::File::create(&tmp_path)?));
+        match extracted {
+            Ok(_) => {
+                if let Some(log) = load_if_DMC(&tmp_path, DMC) {
+                    ret.push((zip_path.join(name), log));
+                }
+            }
+            Err(e) => println!(
+                "ERR: Failed to extract {name} from {}: {e}",
+                zip_path.display()
+            ),
+        }
+    }
+
+    let _ = fs::remove_file(&tmp_path);
+    ret
+}
+
+impl DmcSearch {
+    // Starts the search in the background, 'products' are (name, log directory) pairs.
+    // Returns None for an empty DMC, that would match every file.
+    pub fn start(DMC: &str, products: &[(String, PathBuf)]) -> Option<Self> {
+        let DMC = DMC.trim().to_string();
+        if DMC.is_empty() {
+            return None;
+        }
+
+        let ret = DmcSearch {
+            DMC: DMC.clone(),
+            running: Arc::new(RwLock::new(true)),
+            scanned: Arc::new(RwLock::new(0)),
+            hits: Arc::new(RwLock::new(Vec::new())),
+        };
+
+        let products = products.to_vec();
+        let running_lock = ret.running.clone();
+        let scanned_lock = ret.scanned.clone();
+        let hits_lock = ret.hits.clone();
+
+        thread::spawn(move || {
+            for (product, dir) in products {
+                let mut logs = Vec::new();
+                collect_logs(&dir, &mut logs);
+
+                for source in logs {
+                    let found = match source {
+                        LogSource::File(path) => {
+                            *scanned_lock.write().unwrap() += 1;
+
+                            let found = fs::File::open(&path)
+                                .and_then(|f| contains(f, DMC.as_bytes()))
+                                .unwrap_or(false);
+                            if !found {
+                                continue;
+                            }
+
+                            load_if_DMC(&path, &DMC)
+                                .map(|log| vec![(path, log)])
+                                .unwrap_or_default()
+                        }
+                        LogSource::Zip(path) => search_zip(&path, &DMC, &scanned_lock),
+                    };
+
+                    hits_lock
+                        .write()
+                        .unwrap()
+                        .extend(found.into_iter().map(|(location, log)| DmcHit {
+                            product: product.clone(),
+                            location,
+                            log,
+                        }));
+                }
+            }
+
+            hits_lock
+                .write()
+                .unwrap()
+                .sort_by_key(|h| h.log.get_time_start());
+            *running_lock.write().unwrap() = false;
+        });
+
+        Some(ret)
+    }
+
+    pub fn is_running(&self) -> bool {
+        *self.running.read().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("ICT_dmc_search_{}_{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn contains_finds_the_needle_on_any_line() {
+        let text = "header\nDMC: ABC123\nfooter";
+
+        assert!(contains(text.as_bytes(), b"ABC123").unwrap());
+        assert!(!contains(text.as_bytes(), b"ABC124").unwrap());
+        assert!(contains("ABC123".as_bytes(), b"ABC123").unwrap());
+    }
+
+    #[test]
+    fn only_logs_and_archives_are_collected() {
+        let dir = test_dir("collect");
+        fs::write(dir.join("log1"), "").unwrap();
+        fs::write(dir.join("notes.txt"), "").unwrap();
+        fs::write(dir.join("2024_06_01.zip"), "").unwrap();
+        fs::write(dir.join("backup.zip"), "").unwrap();
+        fs::create_dir(dir.join("2024_05_31")).unwrap();
+        fs::write(dir.join("2024_05_31").join("log2"), "").unwrap();
+        fs::create_dir(dir.join("misc")).unwrap();
+        fs::write(dir.join("misc").join("log3"), "").unwrap();
+
+        let mut logs = Vec::new();
+        collect_logs(&dir, &mut logs);
+
+        let mut files: Vec<PathBuf> = Vec::new();
+        let mut zips: Vec<PathBuf> = Vec::new();
+        for source in logs {
+            match source {
+                LogSource::File(p) => files.push(p),
+                LogSource::Zip(p) => zips.push(p),
+            }
+        }
+        files.sort();
+
+        assert_eq!(
+            files,
+            [dir.join("2024_05_31").join("log2"), dir.join("log1")]
+        );
+        assert_eq!(zips, [dir.join("2024_06_01.zip")]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn zip_entries_without_the_DMC_are_not_parsed() {
+        let dir = test_dir("zip");
+        let zip_path = dir.join("2024_06_01.zip");
+
+        let mut zip = zip::ZipWriter::new(fs::File::create(&zip_path).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        zip.start_file("log1", options).unwrap();
+        zip.write_all(b"DMC: OTHER1\n").unwrap();
+        zip.start_file("log2", options).unwrap();
+        zip.write_all(b"DMC: OTHER2\n").unwrap();
+        zip.start_file("readme.txt", options).unwrap();
+        zip.write_all(b"ABC123\n").unwrap();
+        zip.finish().unwrap();
+
+        let scanned = RwLock::new(0);
+        assert!(search_zip(&zip_path, "ABC123", &scanned).is_empty());
+        assert_eq!(*scanned.read().unwrap(), 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn empty_DMC_is_not_searched() {
+        assert!(DmcSearch::start("  ", &[]).is_none());
+    }
+}
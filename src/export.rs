@@ -0,0 +1,276 @@
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::Path;
+
+use crate::{
+    u64_to_string, BResult, ExportMode, ExportSettings, FlSettings, LogFile, LogFileHandler,
+    TLimit,
+};
+
+/*
+Long (tidy) format export: one row per DMC, index, test and measurement.
+It honors the same settings as the xlsx export, except the orientation.
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Xlsx,
+    Csv,
+    JsonLines,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Xlsx => "xlsx",
+            ExportFormat::Csv => "csv",
+            ExportFormat::JsonLines => "jsonl",
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|x| x.to_str()) {
+            Some("csv") => ExportFormat::Csv,
+            Some("jsonl") | Some("json") => ExportFormat::JsonLines,
+            _ => ExportFormat::Xlsx,
+        }
+    }
+}
+
+const HEADER: [&str; 14] = [
+    "product",
+    "DMC",
+    "DMC_mb",
+    "index",
+    "time_start",
+    "time_end",
+    "board_result",
+    "test",
+    "test_result",
+    "value",
+    "unit",
+    "nominal",
+    "upper_limit",
+    "lower_limit",
+];
+
+type Record = [String; 14];
+
+// Logs selected by the 'only_failed_panels' and 'only_final_logs' settings, ordered by time
+fn select_logs<'a>(lfh: &'a LogFileHandler, settings: &ExportSettings) -> Vec<&'a LogFile> {
+    let mut logs = lfh.get_logs();
+    logs.sort_by_key(|l| l.get_time_start());
+
+    let keys: Vec<(&str, &str, u64, BResult)> = logs
+        .iter()
+        .map(|l| (l.get_DMC_mb(), l.get_DMC(), l.get_time_start(), l.get_result()))
+        .collect();
+    let selected = select(&keys, settings);
+
+    logs.into_iter()
+        .zip(selected)
+        .filter_map(|(log, keep)| keep.then_some(log))
+        .collect()
+}
+
+// 'logs' are (panel DMC, board DMC, start of the test, result), ordered by time.
+// Failed panels keep every board of the panel, final logs are the last test of each board.
+fn select(logs: &[(&str, &str, u64, BResult)], settings: &ExportSettings) -> Vec<bool> {
+    let failed: HashSet<&str> = logs
+        .iter()
+        .filter(|l| l.3 == BResult::Fail)
+        .map(|l| l.0)
+        .collect();
+
+    let mut last: HashMap<&str, u64> = HashMap::new();
+    for log in logs {
+        last.insert(log.1, log.2);
+    }
+
+    logs.iter()
+        .map(|l| {
+            (!settings.only_failed_panels || failed.contains(l.0))
+                && (!settings.only_final_logs || last.get(l.1) == Some(&l.2))
+        })
+        .collect()
+}
+
+// Tests selected by the export mode, None means every test
+fn select_tests(lfh: &LogFileHandler, settings: &ExportSettings) -> Option<HashSet<String>> {
+    match settings.mode {
+        ExportMode::All => None,
+        ExportMode::FailuresOnly => Some(
+            lfh.get_failures(FlSettings::All)
+                .into_iter()
+                .map(|f| f.name)
+                .collect(),
+        ),
+        ExportMode::Manual => Some(
+            settings
+                .list
+                .split_whitespace()
+                .map(|s| s.to_string())
+                .collect(),
+        ),
+    }
+}
+
+fn limits_to_strings(limit: &TLimit) -> [String; 3] {
+    match limit {
+        TLimit::Lim3(nom, ul, ll) => [nom.to_string(), ul.to_string(), ll.to_string()],
+        TLimit::Lim2(ul, ll) => [String::new(), ul.to_string(), ll.to_string()],
+        _ => [String::new(), String::new(), String::new()],
+    }
+}
+
+// Calls 'f' with every selected measurement, in the order of HEADER
+fn for_each_record(
+    lfh: &LogFileHandler,
+    settings: &ExportSettings,
+    mut f: impl FnMut(Record) -> Result<(), Box<dyn std::error::Error>>,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let product = lfh.get_product_id();
+    let tests = select_tests(lfh, settings);
+    let mut rows: usize = 0;
+
+    for log in select_logs(lfh, settings) {
+        for test in log.get_tests() {
+            if tests.as_ref().is_some_and(|t| !t.contains(test.get_name())) {
+                continue;
+            }
+
+            let (result, value) = test.get_result();
+            let [nom, ul, ll] = limits_to_strings(&test.get_limits());
+            f([
+                product.clone(),
+                log.get_DMC().to_string(),
+                log.get_DMC_mb().to_string(),
+                log.get_index().to_string(),
+                u64_to_string(log.get_time_start()),
+                u64_to_string(log.get_time_end()),
+                log.get_result().print(),
+                test.get_name().to_string(),
+                result.print(),
+                value.to_string(),
+                test.get_ttype().unit(),
+                nom,
+                ul,
+                ll,
+            ])?;
+
+            rows += 1;
+        }
+    }
+
+    Ok(rows)
+}
+
+// Numeric fields are written as numbers, empty fields as null
+fn record_to_json(record: Record) -> serde_json::Value {
+    let mut object = serde_json::Map::new();
+
+    for (key, field) in HEADER.iter().zip(record) {
+        let value = match *key {
+            _ if field.is_empty() => serde_json::Value::Null,
+            "index" => serde_json::json!(field.parse::<usize>().ok()),
+            "value" | "nominal" | "upper_limit" | "lower_limit" => {
+                serde_json::json!(field.parse::<f64>().ok())
+            }
+            _ => serde_json::Value::String(field),
+        };
+        object.insert(key.to_string(), value);
+    }
+
+    serde_json::Value::Object(object)
+}
+
+// Writes the measurements of one product to 'out'. Returns the number of rows written.
+pub fn export_long(
+    lfh: &LogFileHandler,
+    settings: &ExportSettings,
+    format: ExportFormat,
+    out: &mut dyn Write,
+    write_header: bool,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    match format {
+        ExportFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(out);
+            if write_header {
+                writer.write_record(HEADER)?;
+            }
+
+            let rows = for_each_record(lfh, settings, |record| {
+                writer.write_record(&record)?;
+                Ok(())
+            })?;
+
+            writer.flush()?;
+            Ok(rows)
+        }
+        ExportFormat::JsonLines => for_each_record(lfh, settings, |record| {
+            serde_json::to_writer(&mut *out, &record_to_json(record))?;
+            out.write_all(b"\n")?;
+            Ok(())
+        }),
+        ExportFormat::Xlsx => Err("xlsx is not a long format!".into()),
+    }
+}
+
+pub fn export_long_to_file(
+    lfh: &LogFileHandler,
+    path: &Path,
+    settings: &ExportSettings,
+    format: ExportFormat,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+    let rows = export_long(lfh, settings, format, &mut file, true)?;
+    file.flush()?;
+
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOGS: [(&str, &str, u64, BResult); 5] = [
+        ("P1", "P1-1", 1, BResult::Pass),
+        ("P1", "P1-2", 1, BResult::Fail),
+        ("P2", "P2-1", 2, BResult::Pass),
+        ("P2", "P2-2", 2, BResult::Pass),
+        ("P1", "P1-2", 3, BResult::Pass),
+    ];
+
+    #[test]
+    fn failed_panels_keep_every_board() {
+        let mut settings = ExportSettings::default();
+        settings.only_failed_panels = true;
+
+        assert_eq!(select(&LOGS, &settings), [true, true, false, false, true]);
+    }
+
+    #[test]
+    fn final_logs_are_the_last_of_each_board() {
+        let mut settings = ExportSettings::default();
+        settings.only_final_logs = true;
+
+        assert_eq!(select(&LOGS, &settings), [true, false, true, true, true]);
+
+        settings.only_failed_panels = true;
+        assert_eq!(select(&LOGS, &settings), [true, false, false, false, true]);
+    }
+
+    #[test]
+    fn json_fields_are_typed() {
+        let mut record: Record = Default::default();
+        record[1] = "DMC".to_string();
+        record[3] = "2".to_string();
+        record[9] = "1.5".to_string();
+
+        let json = record_to_json(record);
+        assert_eq!(json["DMC"], "DMC");
+        assert_eq!(json["index"], 2);
+        assert_eq!(json["value"], 1.5);
+        assert!(json["unit"].is_null());
+    }
+}
@@ -0,0 +1,207 @@
+use egui::Color32;
+use egui_plot::{Bar, BarChart, Line, LineStyle, Plot, PlotPoints, VLine};
+
+use crate::{BResult, TLimit, TResult};
+
+const COLORS: [Color32; 4] = [
+    Color32::BLUE,
+    Color32::GOLD,
+    Color32::DARK_GREEN,
+    Color32::LIGHT_RED,
+];
+
+// (Nominal, UL, LL) of a limit version
+type LimitKey = (Option<f32>, Option<f32>, Option<f32>);
+
+fn limit_key(limit: &TLimit) -> LimitKey {
+    match limit {
+        TLimit::Lim3(nom, ul, ll) => (Some(*nom), Some(*ul), Some(*ll)),
+        TLimit::Lim2(ul, ll) => (None, Some(*ul), Some(*ll)),
+        _ => (None, None, None),
+    }
+}
+
+// Groups the measured values on the index (0 = all) by limit versions, in order of apperance.
+// If 'split' is false, then every value is put into one group, with the newest limits.
+fn group_by_limits(
+    results: &[(u64, usize, TResult, TLimit)],
+    index: usize,
+    split: bool,
+) -> Vec<(LimitKey, Vec<f64>)> {
+    let mut sorted: Vec<&(u64, usize, TResult, TLimit)> = results
+        .iter()
+        .filter(|r| (index == 0 || index == r.1) && r.2 .0 != BResult::Unknown)
+        .collect();
+    sorted.sort_by_key(|r| r.0);
+
+    let mut ret: Vec<(LimitKey, Vec<f64>)> = Vec::new();
+    for r in sorted {
+        let key = limit_key(&r.3);
+        if !split {
+            if ret.is_empty() {
+                ret.push((key, Vec::new()));
+            }
+            ret[0].0 = key;
+            ret[0].1.push(r.2 .1 as f64);
+        } else if let Some(group) = ret.iter_mut().find(|g| g.0 == key) {
+            group.1.push(r.2 .1 as f64);
+        } else {
+            ret.push((key, vec![r.2 .1 as f64]));
+        }
+    }
+
+    ret
+}
+
+pub struct HistogramView {
+    bins: usize,
+}
+
+impl HistogramView {
+    pub fn default() -> Self {
+        HistogramView { bins: 30 }
+    }
+
+    pub fn update(
+        &mut self,
+        ui: &mut egui::Ui,
+        results: &[(u64, usize, TResult, TLimit)],
+        index: usize,
+        split: bool,
+        unit: String,
+        reset: bool,
+    ) {
+        ui.horizontal(|ui| {
+            ui.label("Bins:");
+            ui.add(
+                egui::DragValue::new(&mut self.bins)
+                    .speed(1.0)
+                    .clamp_range(5..=200),
+            );
+
+            if split {
+                ui.label(
+                    egui::RichText::new("Limits changed, splitting by limit versions!")
+                        .color(Color32::RED),
+                );
+            }
+        });
+
+        let groups = group_by_limits(results, index, split);
+        if groups.is_empty() {
+            return;
+        }
+
+        let all_values = groups.iter().flat_map(|g| g.1.iter());
+        let min = all_values.clone().cloned().fold(f64::MAX, f64::min);
+        let max = all_values.cloned().fold(f64::MIN, f64::max);
+
+        // If every value is the same, then use a small range around it
+        let (min, max) = if max > min {
+            (min, max)
+        } else {
+            (
+                min - min.abs().max(1e-9) * 0.01,
+                max + max.abs().max(1e-9) * 0.01,
+            )
+        };
+        let bin_width = (max - min) / self.bins as f64;
+
+        let mut plot = Plot::new("Histogram")
+            .custom_x_axes(vec![egui_plot::AxisHints::new_x()
+                .formatter(crate::y_formatter)
+                .label(unit)])
+            .legend(egui_plot::Legend::default())
+            .height(ui.available_height() - 20.0);
+
+        if reset {
+            plot = plot.reset();
+        }
+
+        plot.show(ui, |plot_ui| {
+            for (i, (limits, values)) in groups.iter().enumerate() {
+                let color = COLORS[i % COLORS.len()];
+                let name = if groups.len() > 1 {
+                    format!("v{}", i + 1)
+                } else {
+                    String::new()
+                };
+                let style = if i + 1 == groups.len() {
+                    LineStyle::Solid
+                } else {
+                    LineStyle::dashed_dense()
+                };
+
+                let mut counts = vec![0usize; self.bins];
+                for v in values {
+                    let bin = (((v - min) / bin_width) as usize).min(self.bins - 1);
+                    counts[bin] += 1;
+                }
+
+                let bars: Vec<Bar> = counts
+                    .iter()
+                    .enumerate()
+                    .map(|(b, c)| {
+                        Bar::new(min + (b as f64 + 0.5) * bin_width, *c as f64)
+                            .width(bin_width)
+                            .fill(color.gamma_multiply(0.5))
+                    })
+                    .collect();
+
+                plot_ui.bar_chart(BarChart::new(bars).color(color).name(format!(
+                    "{} n = {}",
+                    name,
+                    values.len()
+                )));
+
+                // Fitted normal curve, scaled to the histogram
+                let n = values.len() as f64;
+                let mean = values.iter().sum::<f64>() / n;
+                let std_dev = (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n).sqrt();
+                if std_dev > 0.0 {
+                    let scale = n * bin_width / (std_dev * (2.0 * std::f64::consts::PI).sqrt());
+                    let curve: PlotPoints = (0..=200)
+                        .map(|x| {
+                            let x = min + (max - min) * x as f64 / 200.0;
+                            [
+                                x,
+                                scale * (-(x - mean).powi(2) / (2.0 * std_dev.powi(2))).exp(),
+                            ]
+                        })
+                        .collect();
+                    plot_ui.line(
+                        Line::new(curve)
+                            .color(color)
+                            .width(2.0)
+                            .name(format!("Normal {}", name)),
+                    );
+                }
+
+                if let Some(nom) = limits.0 {
+                    plot_ui.vline(
+                        VLine::new(nom)
+                            .color(Color32::GREEN)
+                            .style(style)
+                            .name(format!("Nom {}", name)),
+                    );
+                }
+                if let Some(ul) = limits.1 {
+                    plot_ui.vline(
+                        VLine::new(ul)
+                            .color(Color32::RED)
+                            .style(style)
+                            .name(format!("MAX {}", name)),
+                    );
+                }
+                if let Some(ll) = limits.2 {
+                    plot_ui.vline(
+                        VLine::new(ll)
+                            .color(Color32::RED)
+                            .style(style)
+                            .name(format!("MIN {}", name)),
+                    );
+                }
+            }
+        });
+    }
+}
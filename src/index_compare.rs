@@ -0,0 +1,195 @@
+use egui::Color32;
+use egui_extras::{Column, TableBuilder};
+use egui_plot::{BoxElem, BoxPlot, BoxSpread, HLine, Plot};
+
+use crate::{compute_statistics, BResult, TLimit, TResult, TestStatistics};
+
+// An index is marked, if its mean is further from the overall mean than this many
+// standard deviations of all the measurements. (Not the standard error of the mean,
+// which shrinks with the number of boards and would mark almost every index.)
+const OUTLIER_SIGMA: f64 = 3.0;
+
+// Linear interpolation between the closest ranks, 'sorted' can't be empty
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = p * (sorted.len() - 1) as f64;
+    let low = rank.floor() as usize;
+    let high = rank.ceil() as usize;
+    sorted[low] + (sorted[high] - sorted[low]) * (rank - low as f64)
+}
+
+fn is_outlier(mean: f64, overall: Option<&TestStatistics>) -> bool {
+    overall.is_some_and(|o| o.std_dev > 0.0 && (mean - o.mean).abs() > OUTLIER_SIGMA * o.std_dev)
+}
+
+// One box per board index of the selected test, and the statistics of each index in a table.
+pub fn index_compare_ui(
+    ui: &mut egui::Ui,
+    results: &[(u64, usize, TResult, TLimit)],
+    unit: String,
+    reset: bool,
+    lang: usize,
+) {
+    let mut indexes: Vec<usize> = results.iter().map(|r| r.1).collect();
+    indexes.sort();
+    indexes.dedup();
+
+    if indexes.is_empty() {
+        return;
+    }
+
+    let overall = compute_statistics(results, 0);
+
+    let mut boxes: Vec<BoxElem> = Vec::new();
+    for index in &indexes {
+        let mut values: Vec<f64> = crate::get_selected_values(results, *index)
+            .iter()
+            .map(|v| v.1 as f64)
+            .collect();
+        if values.is_empty() {
+            continue;
+        }
+        values.sort_by(|a, b| a.total_cmp(b));
+
+        let spread = BoxSpread::new(
+            values[0],
+            percentile(&values, 0.25),
+            percentile(&values, 0.5),
+            percentile(&values, 0.75),
+            values[values.len() - 1],
+        );
+
+        boxes.push(
+            BoxElem::new(*index as f64, spread)
+                .name(format!("{}.", index))
+                .box_width(0.6)
+                .whisker_width(0.3)
+                .fill(Color32::LIGHT_BLUE.gamma_multiply(0.5))
+                .stroke(egui::Stroke::new(1.0, Color32::BLUE)),
+        );
+    }
+
+    let mut plot = Plot::new("Index comparison")
+        .custom_y_axes(vec![egui_plot::AxisHints::new_y()
+            .formatter(crate::y_formatter)
+            .label(unit)])
+        .height((ui.available_height() * 0.6).max(150.0));
+
+    if reset {
+        plot = plot.reset();
+    }
+
+    plot.show(ui, |plot_ui| {
+        plot_ui.box_plot(BoxPlot::new(boxes));
+
+        if let Some((ll, ul)) = overall.as_ref().and_then(|s| s.limits) {
+            plot_ui.hline(HLine::new(ul).color(Color32::RED).name("MAX"));
+            plot_ui.hline(HLine::new(ll).color(Color32::RED).name("MIN"));
+        }
+    });
+
+    ui.separator();
+
+    let text_color = ui.visuals().text_color();
+    ui.push_id("index stats", |ui| {
+        TableBuilder::new(ui)
+            .striped(true)
+            .column(Column::initial(50.0))
+            .column(Column::initial(50.0))
+            .columns(Column::initial(100.0), 5)
+            .column(Column::remainder())
+            .header(20.0, |mut header| {
+                for name in [
+                    "Index",
+                    "n",
+                    crate::MESSAGE_P[crate::MEAN][lang],
+                    "σ",
+                    "Min",
+                    "Max",
+                    "Cpk",
+                    "NOK",
+                ] {
+                    header.col(|ui| {
+                        ui.strong(name);
+                    });
+                }
+            })
+            .body(|mut body| {
+                for index in &indexes {
+                    let Some(stats) = compute_statistics(results, *index) else {
+                        continue;
+                    };
+                    let failures = results
+                        .iter()
+                        .filter(|r| r.1 == *index && r.2 .0 == BResult::Fail)
+                        .count();
+                    let color = if is_outlier(stats.mean, overall.as_ref()) || failures > 0 {
+                        Color32::RED
+                    } else {
+                        text_color
+                    };
+
+                    body.row(16.0, |mut row| {
+                        row.col(|ui| {
+                            ui.colored_label(color, format!("{}.", index));
+                        });
+                        row.col(|ui| {
+                            ui.label(format!("{}", stats.count));
+                        });
+                        row.col(|ui| {
+                            ui.colored_label(color, format!("{:+1.4E}", stats.mean));
+                        });
+                        row.col(|ui| {
+                            ui.label(format!("{:1.4E}", stats.std_dev));
+                        });
+                        row.col(|ui| {
+                            ui.label(format!("{:+1.4E}", stats.min));
+                        });
+                        row.col(|ui| {
+                            ui.label(format!("{:+1.4E}", stats.max));
+                        });
+                        row.col(|ui| {
+                            ui.label(stats.cpk.map_or("-".to_string(), |x| format!("{:.2}", x)));
+                        });
+                        row.col(|ui| {
+                            ui.colored_label(color, format!("{}", failures));
+                        });
+                    });
+                }
+            });
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overall(mean: f64, std_dev: f64) -> TestStatistics {
+        TestStatistics {
+            count: 100,
+            mean,
+            std_dev,
+            min: mean - 4.0 * std_dev,
+            max: mean + 4.0 * std_dev,
+            limits: None,
+            cp: None,
+            cpk: None,
+            near_limits: None,
+        }
+    }
+
+    #[test]
+    fn outliers_are_three_sigma_away() {
+        let o = overall(10.0, 0.5);
+
+        assert!(!is_outlier(10.9, Some(&o)));
+        assert!(!is_outlier(8.6, Some(&o)));
+        assert!(is_outlier(11.6, Some(&o)));
+        assert!(is_outlier(8.4, Some(&o)));
+    }
+
+    #[test]
+    fn no_outliers_without_spread() {
+        assert!(!is_outlier(12.0, Some(&overall(10.0, 0.0))));
+        assert!(!is_outlier(12.0, None));
+    }
+}
@@ -17,8 +17,12 @@ const TOTAL: usize = 9;
 const AUTO_UPDATE: usize = 12;
 const AU_DONE_1: usize = 13;
 const AU_DONE_2: usize = 14;
+const PRODUCT_FILTER: usize = 15;
+const ALL_PRODUCTS: usize = 16;
+const SELECT_PRODUCT: usize = 17;
+const COMBINED: usize = 18;
 
-const MESSAGE:  [[&str;2];15] = [
+const MESSAGE:  [[&str;2];19] = [
     ["Váltás magyar nyelvre!",  "Language changed to English!"],
     ["Logok betöltése",         "Loadings logs"],
     ["Műszak",                  "Shift"],
@@ -34,6 +38,10 @@ const MESSAGE:  [[&str;2];15] = [
     ["Automata frissítés:",                 "Automatic update:"],
     ["Automata frissítés befejeződött ",    "Automatic update done in "],
     ["ms alatt, új logok: ",                "ms, new logs: "],
+    ["Termék:",                 "Product:"],
+    ["Összes",                  "All"],
+    ["Válassz ki egy terméket a szűrőben!", "Select a product in the product filter!"],
+    ["Betöltés ezekkel együtt:",            "Load together with:"],
 ];
 
 // EXPORT:
@@ -52,8 +60,10 @@ const SAVE: usize = 10;
 const LIMIT_W:  usize = 11;
 const LIMIT_W2:  usize = 12;
 const EXPORT_FINAL_ONLY: usize = 13;
+const EXPORT_STATS: usize = 14;
+const EXPORT_FORMAT: usize = 15;
 
-const MESSAGE_E: [[&str;2];14] = [
+const MESSAGE_E: [[&str;2];16] = [
     ["💾 Export",                  "💾 Export"],
     ["Beállítások:",            "Settings:"],
     ["Vertikális elrendezés (1 sor = 1 log/pcb)",   "Vertical orientation (1 row = 1 log/pcb)"],
@@ -69,6 +79,8 @@ const MESSAGE_E: [[&str;2];14] = [
     ["Figyelmeztetés: teszt",                                   "Warning: test"],
     ["limitje változott! Ez a táblázatban nem lesz látható!",   "has limit changes! This won't be visile in the spreadsheet!"],
     ["Csak a végső logok exportálása",   "Export only the final logs"],
+    ["Statisztikák mentése (Cp/Cpk)",    "Save statistics (Cp/Cpk)"],
+    ["Formátum:",               "Format:"],
 ];
 
 // HOURLY + MULTIBOARDS:
@@ -88,7 +100,118 @@ const MESSAGE_H: [[&str;2];4] = [
 // PLOT:
 
 const PLOT_LABEL: usize = 0;
+const MEAN: usize = 1;
+const NEAR_LIMITS: usize = 2;
 
-const MESSAGE_P: [[&str;2];1] = [
+const MESSAGE_P: [[&str;2];3] = [
     ["📊 Grafikon",                "📊 Plotting"],
-];
\ No newline at end of file
+    ["Átlag",                   "Mean"],
+    ["Limit közelében",         "Near limits"],
+];
+// FAILURE ANALYSIS:
+
+const PARETO_LABEL: usize = 0;
+const CUMULATIVE: usize = 1;
+const TOP: usize = 2;
+const TREND_LABEL: usize = 3;
+const HOUR_B: usize = 4;
+const DAY_B: usize = 5;
+const COUNT: usize = 6;
+const RATE: usize = 7;
+const RETEST_LABEL: usize = 8;
+const WEAR_LABEL: usize = 9;
+const NO_WEAR: usize = 10;
+const INDEX: usize = 11;
+const WEAR_RISING: usize = 12;
+const WEAR_MATRIX: usize = 13;
+
+const MESSAGE_F: [[&str;2];14] = [
+    ["📉 Pareto",                  "📉 Pareto"],
+    ["Kumulatív %",             "Cumulative %"],
+    ["Első:",                   "Top:"],
+    ["📈 Trend",                   "📈 Trend"],
+    ["Óra",                     "Hour"],
+    ["Nap",                     "Day"],
+    ["Darab",                   "Count"],
+    ["Arány (%)",               "Rate (%)"],
+    ["🔁 Újratesztek",             "🔁 Retests"],
+    ["📌 Fixture kopás",           "📌 Fixture wear"],
+    ["Nincs emelkedő hibaarány.",   "No rising failure rates."],
+    ["Index",                   "Index"],
+    ["hibák emelkednek, kezdete:",  "failures rising since"],
+    ["Hibás panelek indexenként és tesztcsaládonként:",    "Failing boards by index and test family:"],
+];
+
+// RETEST ANALYSIS:
+
+const BOARDS: usize = 0;
+const FAILED_FIRST: usize = 1;
+const RETESTED: usize = 2;
+const AVG_RETESTS: usize = 3;
+const TIME_BETWEEN: usize = 4;
+const MORE_THAN: usize = 5;
+const ATTEMPTS: usize = 6;
+const SPAN: usize = 7;
+const TEST: usize = 8;
+const PASSED_RETEST: usize = 9;
+const FAILED_AGAIN: usize = 10;
+const FALSE_CALL: usize = 11;
+const TIME_TO_RETEST: usize = 12;
+
+const MESSAGE_R: [[&str;2];13] = [
+    ["Panelek:",                "Boards:"],
+    ["Első teszten bukott",     "Failed first test"],
+    ["Újratesztelt panelek:",   "Retested boards:"],
+    ["Átlagos újratesztek (összes / bukott):",      "Average retests (all / failed):"],
+    ["Idő a tesztek között (átlag / max):",         "Time between attempts (avg / max):"],
+    ["Több próbálkozás, mint:", "More attempts than:"],
+    ["Próbálkozás",             "Attempts"],
+    ["Időtartam",               "Span"],
+    ["Teszt",                   "Test"],
+    ["Újrateszten jó",          "Passed on retest"],
+    ["Újra bukott",             "Failed again"],
+    ["Téves hiba",              "False call"],
+    ["Idő az újratesztig",      "Time to retest"],
+];
+
+// ALARMS:
+
+const ALARMS_LABEL: usize = 0;
+const ACK: usize = 1;
+const HISTORY: usize = 2;
+const RULES: usize = 3;
+const NO_RULES: usize = 4;
+
+const MESSAGE_A: [[&str;2];5] = [
+    ["🔔 Riasztások",              "🔔 Alarms"],
+    ["Nyugtázás",               "Acknowledge"],
+    ["Előzmények:",             "History:"],
+    ["Szabályok:",              "Rules:"],
+    ["Nincsenek szabályok (alarms fájl).",  "No rules defined (alarms file)."],
+];
+
+// REPAIRS:
+
+const REPAIRS_LABEL: usize = 0;
+const REPAIRS: usize = 1;
+const RETESTED_R: usize = 2;
+const FIXED: usize = 3;
+const DEFECT_CODE: usize = 4;
+const ACTION: usize = 5;
+const COMMENT: usize = 6;
+const RECORDED: usize = 7;
+const RETEST_RESULT: usize = 8;
+const EXPORT_REPAIRS: usize = 9;
+
+const MESSAGE_RP: [[&str;2];10] = [
+    ["🔧 Javítások",               "🔧 Repairs"],
+    ["Javítások",               "Repairs"],
+    ["Újratesztelve",           "Retested"],
+    ["Javítva",                 "Fixed"],
+    ["Hibakód",                 "Defect code"],
+    ["Beavatkozás",             "Action"],
+    ["Megjegyzés",              "Comment"],
+    ["Rögzítve",                "Recorded"],
+    ["Újrateszt",               "Retest result"],
+    ["Exportálás",              "Export"],
+];
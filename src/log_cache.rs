@@ -0,0 +1,281 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::LogFile;
+
+/*
+Local cache of the parsed logfiles, so reloading a long time period doesn't have to read
+and parse every file from the network drive again. There is one cache file per log directory,
+the entries are keyed by the path of the log, and they are only valid while the size and the
+last modification time of the log are unchanged. Entries not used for CACHE_MAX_AGE are dropped.
+The cache is discarded when the program or the ICT_logfile version changes, as the parser
+or the LogFile struct might have changed. It needs the "serde" feature of ICT_logfile
+(LogFile: Serialize + Deserialize) and LogFile::load for parsing a single file.
+*/
+
+const CACHE_DIR: &str = ".\\cache";
+const CACHE_MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+// A hit only marks the cache as changed, if the entry was last used before this,
+// so loading the same files again doesn't rewrite the whole cache every time.
+const LAST_USED_RESOLUTION: Duration = Duration::from_secs(24 * 60 * 60);
+
+// Set by build.rs from Cargo.lock
+const CACHE_VERSION: &str = concat!(
+    env!("CARGO_PKG_VERSION"),
+    "+ICT_logfile-",
+    env!("ICT_LOGFILE_VERSION")
+);
+
+#[derive(Serialize, Deserialize)]
+pub struct CacheEntry {
+    modified: SystemTime,
+    size: u64,
+    last_used: SystemTime,
+    data: Vec<u8>, // bincode serialized LogFile
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheFile {
+    version: String,
+    entries: HashMap<PathBuf, CacheEntry>,
+}
+
+// Changes to the cache made by one 'load' call
+pub enum CacheUpdate {
+    Hit(PathBuf),
+    New(PathBuf, CacheEntry),
+    None, // parsed, but it couldn't be cached
+}
+
+pub struct LogCache {
+    path: PathBuf,
+    entries: HashMap<PathBuf, CacheEntry>,
+    changed: bool,
+    hits: usize,
+    misses: usize,
+}
+
+// Name of the cache file for the log directory, e.g. "\\server\ict\product" -> "server_ict_product.bin"
+fn cache_file_name(log_dir: &Path) -> String {
+    let name: String = log_dir
+        .to_string_lossy()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+
+    format!("{}.bin", name.trim_matches('_'))
+}
+
+impl LogCache {
+    // Opens the cache of the log directory. If it doesn't exist or it can't be read, then starts with an empty one.
+    pub fn open(log_dir: &Path) -> Self {
+        let path = PathBuf::from(CACHE_DIR).join(cache_file_name(log_dir));
+
+        let entries = match fs::File::open(&path) {
+            Ok(file) => match bincode::deserialize_from::<_, CacheFile>(BufReader::new(file)) {
+                Ok(cache) if cache.version == CACHE_VERSION => cache.entries,
+                Ok(_) => {
+                    println!("INFO: Cache {:?} is from a different version, discarding it.", path);
+                    HashMap::new()
+                }
+                Err(e) => {
+                    println!("ERR: Failed to read cache {:?}: {}", path, e);
+                    HashMap::new()
+                }
+            },
+            Err(_) => HashMap::new(),
+        };
+
+        LogCache {
+            path,
+            entries,
+            changed: false,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    // Returns the log from the cache if the file is unchanged, otherwise parses it.
+    // It doesn't modify the cache, so it can be called from multiple threads at once,
+    // the returned CacheUpdate has to be applied with 'apply' afterwards.
+    pub fn load(&self, path: &Path) -> Option<(LogFile, CacheUpdate)> {
+        let metadata = path.metadata().ok()?;
+        let modified = metadata.modified().ok()?;
+        let size = metadata.len();
+
+        if let Some(entry) = self.entries.get(path) {
+            if entry.modified == modified && entry.size == size {
+                if let Ok(log) = bincode::deserialize::<LogFile>(&entry.data) {
+                    return Some((log, CacheUpdate::Hit(path.to_path_buf())));
+                }
+            }
+        }
+
+        let log = match LogFile::load(path) {
+            Ok(log) => log,
+            Err(e) => {
+                println!("ERR: Failed to load {:?}: {}", path, e);
+                return None;
+            }
+        };
+
+        let update = match bincode::serialize(&log) {
+            Ok(data) => CacheUpdate::New(
+                path.to_path_buf(),
+                CacheEntry {
+                    modified,
+                    size,
+                    last_used: SystemTime::now(),
+                    data,
+                },
+            ),
+            Err(e) => {
+                println!("ERR: Failed to serialize {:?}: {}", path, e);
+                CacheUpdate::None
+            }
+        };
+
+        Some((log, update))
+    }
+
+    pub fn apply(&mut self, updates: Vec<CacheUpdate>) {
+        let now = SystemTime::now();
+
+        for update in updates {
+            match update {
+                CacheUpdate::Hit(path) => {
+                    if let Some(entry) = self.entries.get_mut(&path) {
+                        if now
+                            .duration_since(entry.last_used)
+                            .is_ok_and(|age| age > LAST_USED_RESOLUTION)
+                        {
+                            self.changed = true;
+                        }
+                        entry.last_used = now;
+                    }
+                    self.hits += 1;
+                }
+                CacheUpdate::New(path, entry) => {
+                    self.entries.insert(path, entry);
+                    self.changed = true;
+                    self.misses += 1;
+                }
+                CacheUpdate::None => {
+                    self.misses += 1;
+                }
+            }
+        }
+    }
+
+    // Drops the old entries and writes the cache to disk, if entries were added or dropped.
+    pub fn save(&mut self) {
+        println!(
+            "INFO: Cache hits: {}, parsed logs: {}",
+            self.hits, self.misses
+        );
+
+        let now = SystemTime::now();
+        let count = self.entries.len();
+        self.entries.retain(|_, e| {
+            now.duration_since(e.last_used)
+                .map_or(true, |age| age < CACHE_MAX_AGE)
+        });
+
+        if !self.changed && self.entries.len() == count {
+            return;
+        }
+
+        let cache = CacheFile {
+            version: CACHE_VERSION.to_string(),
+            entries: std::mem::take(&mut self.entries),
+        };
+
+        // Writes to a temporary file first, so a crash can't leave a half written cache behind.
+        let tmp_path = self.path.with_extension("tmp");
+        let result = fs::create_dir_all(CACHE_DIR)
+            .map_err(|e| e.to_string())
+            .and_then(|_| fs::File::create(&tmp_path).map_err(|e| e.to_string()))
+            .and_then(|file| {
+                let mut writer = BufWriter::new(file);
+                bincode::serialize_into(&mut writer, &cache).map_err(|e| e.to_string())?;
+                writer.flush().map_err(|e| e.to_string())
+            })
+            .and_then(|_| fs::rename(&tmp_path, &self.path).map_err(|e| e.to_string()));
+
+        match result {
+            Ok(_) => self.changed = false,
+            Err(e) => println!("ERR: Failed to write cache {:?}: {}", self.path, e),
+        }
+
+        self.entries = cache.entries;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(last_used: SystemTime) -> CacheEntry {
+        CacheEntry {
+            modified: SystemTime::UNIX_EPOCH,
+            size: 0,
+            last_used,
+            data: Vec::new(),
+        }
+    }
+
+    fn cache(entries: Vec<(&str, CacheEntry)>) -> LogCache {
+        LogCache {
+            path: PathBuf::from("test.bin"),
+            entries: entries
+                .into_iter()
+                .map(|(p, e)| (PathBuf::from(p), e))
+                .collect(),
+            changed: false,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    #[test]
+    fn file_name_from_log_dir() {
+        assert_eq!(
+            cache_file_name(Path::new("\\\\server\\ict\\product")),
+            "server_ict_product.bin"
+        );
+    }
+
+    #[test]
+    fn recent_hits_dont_change_the_cache() {
+        let mut cache = cache(vec![("a", entry(SystemTime::now()))]);
+        cache.apply(vec![CacheUpdate::Hit(PathBuf::from("a"))]);
+
+        assert!(!cache.changed);
+        assert_eq!(cache.hits, 1);
+    }
+
+    #[test]
+    fn old_hits_and_new_entries_change_the_cache() {
+        let old = SystemTime::now() - 2 * LAST_USED_RESOLUTION;
+        let mut hit = cache(vec![("a", entry(old))]);
+        hit.apply(vec![CacheUpdate::Hit(PathBuf::from("a"))]);
+
+        assert!(hit.changed);
+        assert!(hit.entries[Path::new("a")].last_used > old);
+
+        let mut new = cache(Vec::new());
+        new.apply(vec![
+            CacheUpdate::New(PathBuf::from("b"), entry(SystemTime::now())),
+            CacheUpdate::None,
+        ]);
+
+        assert!(new.changed);
+        assert_eq!(new.misses, 2);
+        assert!(new.entries.contains_key(Path::new("b")));
+    }
+}
@@ -1,21 +1,60 @@
-use crate::LogFileHandler;
+use crate::{
+    attempts_of, board_at_index, boards_of_panel, compare_table, print_ticket, report_table,
+    u64_to_datetime, Attempt, BResult, BarcodeScanner, DmcHit, DmcSearch, LogFileHandler,
+    RepairForm, RepairLog, ReportFilter,
+};
+use std::path::PathBuf;
 use std::sync::{Arc, RwLock};
 
 pub struct LogInfoWindow {
     enabled: bool,
     DMC: String,
     report: String,
+    attempts: Vec<Attempt>, // of the shown DMC, ordered by time
+    selected_attempt: usize,
+    filter: ReportFilter,
+
+    // Compare mode: the selected attempt (A) is compared with another attempt of the board,
+    // or with an attempt of another board (B)
+    compare: bool,
+    compare_DMC: String,
+    other_attempts: Option<Vec<Attempt>>, // None = the attempts of the same board
+    compare_selected: usize,
+    differences_only: bool,
+
+    repair_form: RepairForm,
 
     search_bar: String,
+
+    products: Vec<(String, PathBuf)>, // (name, log directory), for the global search
+    global_search: Option<DmcSearch>,
+    selected_hit: Option<usize>,
+    select_last_hit: bool, // select the newest attempt when the global search finishes
+
+    verdict: bool, // big PASS/FAIL of the last attempt, for scanned boards
 }
 
 impl LogInfoWindow {
-    pub fn default() -> Self {
+    pub fn default(products: Vec<(String, PathBuf)>) -> Self {
         Self {
             enabled: false,
             DMC: String::new(),
             report: String::new(),
+            attempts: Vec::new(),
+            selected_attempt: 0,
+            filter: ReportFilter::default(),
+            compare: false,
+            compare_DMC: String::new(),
+            other_attempts: None,
+            compare_selected: 0,
+            differences_only: false,
+            repair_form: RepairForm::default(),
             search_bar: String::new(),
+            products,
+            global_search: None,
+            selected_hit: None,
+            select_last_hit: false,
+            verdict: false,
         }
     }
 
@@ -23,58 +62,239 @@ impl LogInfoWindow {
         self.enabled = true;
     }
 
+    // Shows the attempts of 'board', the last one selected
+    fn set_attempts(&mut self, lfh: &LogFileHandler, board: &str) {
+        self.attempts = attempts_of(lfh, board);
+        self.selected_attempt = self.attempts.len().saturating_sub(1);
+        self.reset_compare();
+    }
+
+    // By default the selected attempt is compared with the next one,
+    // or the last two attempts if the last one is selected.
+    fn reset_compare(&mut self) {
+        self.other_attempts = None;
+        self.compare_DMC.clear();
+
+        if self.compare {
+            let last = self.attempts.len().saturating_sub(1);
+            self.selected_attempt = self.selected_attempt.min(last.saturating_sub(1));
+            self.compare_selected = (self.selected_attempt + 1).min(last);
+        }
+    }
+
+    fn compared_attempt(&self) -> Option<&Attempt> {
+        self.other_attempts
+            .as_ref()
+            .unwrap_or(&self.attempts)
+            .get(self.compare_selected)
+    }
+
+    // Steps through the attempts of the board. When comparing with the same board,
+    // the compared attempt moves with it.
+    fn step(&mut self, forward: bool) {
+        let last = self.attempts.len().saturating_sub(1);
+        let same_board = self.compare && self.other_attempts.is_none();
+
+        if forward && self.selected_attempt < last && !(same_board && self.compare_selected >= last)
+        {
+            self.selected_attempt += 1;
+            if same_board {
+                self.compare_selected += 1;
+            }
+        } else if !forward && self.selected_attempt > 0 {
+            self.selected_attempt -= 1;
+            if same_board {
+                self.compare_selected = self.compare_selected.saturating_sub(1);
+            }
+        }
+    }
+
     pub fn open_first_NOK(&mut self, target_DMC: String, lfh: Arc<RwLock<LogFileHandler>>) {
-        if let Some(report) = lfh.read().unwrap().get_report_for_SB_NOK(&target_DMC) {
+        self.verdict = false;
+        let lfh = lfh.read().unwrap();
+        if let Some(report) = lfh.get_report_for_SB_NOK(&target_DMC) {
             self.enabled = true;
             self.DMC = target_DMC.clone();
             self.search_bar = target_DMC;
             self.report = report;
+
+            // The first failed board of the panel, with its first failed attempt selected
+            let boards = boards_of_panel(&lfh, &self.DMC);
+            self.attempts.clear();
+            for board in &boards {
+                let attempts = attempts_of(&lfh, board);
+                if let Some(i) = attempts.iter().position(|a| a.result == BResult::Fail) {
+                    self.attempts = attempts;
+                    self.selected_attempt = i;
+                    break;
+                }
+            }
+            if self.attempts.is_empty() {
+                if let Some(board) = boards.first() {
+                    self.set_attempts(&lfh, board);
+                }
+            }
         }
     }
 
+    // Opens the board on 'index' of the panel, with the attempt started at 'time_start' selected
     pub fn open_w_index(
         &mut self,
         target_DMC: String,
         index: usize,
+        time_start: u64,
         lfh: Arc<RwLock<LogFileHandler>>,
     ) {
-        if let Some(report) = lfh
-            .read()
-            .unwrap()
-            .get_report_for_SB_w_index(&target_DMC, index)
-        {
+        self.verdict = false;
+        let lfh = lfh.read().unwrap();
+        if let Some(report) = lfh.get_report_for_SB_w_index(&target_DMC, index) {
             self.enabled = true;
             self.DMC = target_DMC.clone();
             self.search_bar = target_DMC;
             self.report = report;
+
+            match board_at_index(&lfh, &self.DMC, index) {
+                Some(board) => {
+                    self.set_attempts(&lfh, &board);
+                    if let Some(i) = self
+                        .attempts
+                        .iter()
+                        .position(|a| a.time_start == time_start)
+                    {
+                        self.selected_attempt = i;
+                        self.reset_compare();
+                    }
+                }
+                None => self.attempts.clear(),
+            }
         }
     }
 
     pub fn open(&mut self, target_DMC: String, lfh: Arc<RwLock<LogFileHandler>>) {
-        if let Some(report) = lfh.read().unwrap().get_report_for_SB(&target_DMC) {
+        self.verdict = false;
+        let lfh = lfh.read().unwrap();
+        if let Some(report) = lfh.get_report_for_SB(&target_DMC) {
             self.enabled = true;
+            self.set_attempts(&lfh, &target_DMC);
             self.DMC = target_DMC.clone();
             self.search_bar = target_DMC;
             self.report = report;
         }
     }
 
+    // Searches the DMC on the disk, and shows its newest attempt when found
+    pub fn open_global(&mut self, target_DMC: String) {
+        self.enabled = true;
+        self.verdict = false;
+        self.attempts.clear();
+        self.report.clear();
+        self.global_search = DmcSearch::start(&target_DMC, &self.products);
+        self.selected_hit = None;
+        self.select_last_hit = true;
+        self.DMC = target_DMC.clone();
+        self.search_bar = target_DMC;
+    }
+
+    // Shows the result of the last attempt in big letters, until an other board is opened
+    pub fn show_verdict(&mut self) {
+        self.verdict = true;
+    }
+
     pub fn enabled(&self) -> bool {
         self.enabled
     }
 
-    pub fn update(&mut self, ctx: &egui::Context, lfh: Arc<RwLock<LogFileHandler>>) {
+    fn select_hit(&mut self, hits: &[DmcHit], DMC: &str, i: usize) {
+        self.DMC = DMC.to_string();
+        self.report.clear();
+        self.attempts = hits
+            .iter()
+            .map(|h| {
+                let mut attempt = Attempt::new(&h.log);
+                attempt.source = h.location.clone();
+                attempt
+            })
+            .collect();
+        self.selected_attempt = i;
+        self.selected_hit = Some(i);
+        self.reset_compare();
+    }
+
+    // Attempts found by the global search, clicking one shows its report
+    fn global_search_results(&mut self, ui: &mut egui::Ui, search: &DmcSearch) {
+        ui.separator();
+
+        let scanned = *search.scanned.read().unwrap();
+        let hits = search.hits.read().unwrap();
+        ui.horizontal(|ui| {
+            if search.is_running() {
+                ui.spinner();
+            }
+            ui.label(format!(
+                "{}: {} attempts found, {} files scanned",
+                search.DMC,
+                hits.len(),
+                scanned
+            ));
+        });
+
+        egui::ScrollArea::vertical()
+            .id_source("global hits")
+            .max_height(150.0)
+            .show(ui, |ui| {
+                for (i, hit) in hits.iter().enumerate() {
+                    let time = u64_to_datetime(hit.log.get_time_start())
+                        .map_or(String::new(), |t| t.format("%F %T").to_string());
+                    let text = egui::RichText::new(format!(
+                        "{}  {}  {}",
+                        time,
+                        hit.product,
+                        hit.log.get_result().print()
+                    ))
+                    .color(hit.log.get_result().into_dark_color());
+
+                    if ui
+                        .selectable_label(self.selected_hit == Some(i), text)
+                        .on_hover_text(hit.location.to_string_lossy())
+                        .clicked()
+                    {
+                        self.select_hit(&hits, &search.DMC, i);
+                    }
+                }
+            });
+
+        if self.select_last_hit && !search.is_running() {
+            self.select_last_hit = false;
+            if !hits.is_empty() {
+                self.select_hit(&hits, &search.DMC, hits.len() - 1);
+            }
+        }
+    }
+
+    // 'lfh' are the handlers the search bar looks in, the first hit is shown.
+    // Returns the code scanned while the window had the focus.
+    pub fn update(
+        &mut self,
+        ctx: &egui::Context,
+        lfh: &[Arc<RwLock<LogFileHandler>>],
+        repairs: &mut RepairLog,
+        scanner: &mut BarcodeScanner,
+    ) -> Option<String> {
+        let mut scanned = None;
+
         ctx.show_viewport_immediate(
             egui::ViewportId::from_hash_of("LIWindow"),
             egui::ViewportBuilder::default()
                 .with_title(self.DMC.clone())
-                .with_inner_size([400.0, 400.0]),
+                .with_inner_size([800.0, 500.0]),
             |ctx, class| {
                 assert!(
                     class == egui::ViewportClass::Immediate,
                     "This egui backend doesn't support multiple viewports"
                 );
 
+                scanned = scanner.feed(ctx);
+
                 egui::CentralPanel::default().show(ctx, |ui| {
                     ui.spacing_mut().scroll = egui::style::ScrollStyle::solid();
 
@@ -86,27 +306,156 @@ impl LogInfoWindow {
                         );
 
                         if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
-                            if let Some(report) =
-                                lfh.read().unwrap().get_report_for_SB(&self.search_bar)
-                            {
-                                self.DMC = self.search_bar.clone();
-                                self.report = report;
+                            for l in lfh {
+                                let l = l.read().unwrap();
+                                if let Some(report) = l.get_report_for_SB(&self.search_bar) {
+                                    self.DMC = self.search_bar.clone();
+                                    self.report = report;
+                                    self.set_attempts(&l, &self.search_bar.clone());
+                                    break;
+                                }
                             }
 
                             ctx.memory_mut(|mem| mem.request_focus(response.id))
                         }
+
+                        let running = self.global_search.as_ref().is_some_and(|s| s.is_running());
+                        if ui
+                            .add_enabled(
+                                !running && !self.search_bar.trim().is_empty(),
+                                egui::Button::new("🌐"),
+                            )
+                            .on_hover_text(
+                                "Search in the logs of every product, and in the archives",
+                            )
+                            .clicked()
+                        {
+                            self.global_search = DmcSearch::start(&self.search_bar, &self.products);
+                            self.selected_hit = None;
+                        }
                     });
 
+                    if let Some(search) = self.global_search.take() {
+                        self.global_search_results(ui, &search);
+                        self.global_search = Some(search);
+                    }
+
                     ui.separator();
 
-                    egui::ScrollArea::vertical()
-                        .auto_shrink(false)
-                        .show(ui, |ui| {
-                            ui.add(
-                                egui::TextEdit::multiline(&mut self.report.as_str())
-                                    .desired_width(f32::INFINITY),
+                    if self.verdict {
+                        if let Some(last) = self.attempts.last() {
+                            ui.horizontal(|ui| {
+                                ui.label(
+                                    egui::RichText::new(last.result.print())
+                                        .color(last.result.into_color())
+                                        .size(64.0)
+                                        .strong(),
+                                );
+                                ui.label(
+                                    egui::RichText::new(format!(
+                                        "{}\n{}",
+                                        last.DMC,
+                                        u64_to_datetime(last.time_start)
+                                            .map_or(String::new(), |t| {
+                                                t.format("%F %T").to_string()
+                                            })
+                                    ))
+                                    .size(20.0),
+                                );
+                            });
+                            ui.separator();
+                        }
+                    }
+
+                    // The plain text report is only shown if the logs of the board are not available
+                    if self.attempts.is_empty() {
+                        egui::ScrollArea::vertical()
+                            .auto_shrink(false)
+                            .show(ui, |ui| {
+                                ui.add(
+                                    egui::TextEdit::multiline(&mut self.report.as_str())
+                                        .desired_width(f32::INFINITY),
+                                );
+                            });
+                        return;
+                    }
+
+                    ui.horizontal(|ui| {
+                        if ui.button("◀").clicked() {
+                            self.step(false);
+                        }
+                        attempt_selector(ui, "attempt", &self.attempts, &mut self.selected_attempt);
+                        if ui.button("▶").clicked() {
+                            self.step(true);
+                        }
+
+                        ui.separator();
+                        if ui.checkbox(&mut self.compare, "Compare").changed() {
+                            self.reset_compare();
+                        }
+
+                        if ui.button("🖨 Print ticket").clicked() {
+                            print_ticket(&self.attempts);
+                        }
+
+                        ui.separator();
+                        self.filter.ui(ui);
+                    });
+
+                    if self.compare {
+                        ui.horizontal(|ui| {
+                            ui.label("with DMC:");
+                            let response = ui.add(
+                                egui::TextEdit::singleline(&mut self.compare_DMC)
+                                    .hint_text("same board")
+                                    .desired_width(200.0),
                             );
+                            if response.lost_focus()
+                                && ui.input(|i| i.key_pressed(egui::Key::Enter))
+                            {
+                                if self.compare_DMC.is_empty() {
+                                    self.reset_compare();
+                                } else {
+                                    if let Some(attempts) = lfh
+                                        .iter()
+                                        .map(|l| attempts_of(&l.read().unwrap(), &self.compare_DMC))
+                                        .find(|a| !a.is_empty())
+                                    {
+                                        self.compare_selected = attempts.len() - 1;
+                                        self.other_attempts = Some(attempts);
+                                    }
+                                }
+                            }
+
+                            let compared = self.other_attempts.as_ref().unwrap_or(&self.attempts);
+                            attempt_selector(
+                                ui,
+                                "compared attempt",
+                                compared,
+                                &mut self.compare_selected,
+                            );
+
+                            ui.checkbox(&mut self.differences_only, "Differences only");
                         });
+                    }
+
+                    let Some(attempt) = self.attempts.get(self.selected_attempt) else {
+                        return;
+                    };
+                    attempt_header(ui, if self.compare { "A: " } else { "" }, attempt);
+
+                    if self.compare {
+                        let Some(compared) = self.compared_attempt() else {
+                            return;
+                        };
+                        attempt_header(ui, "B: ", compared);
+                        ui.separator();
+                        compare_table(ui, attempt, compared, &self.filter, self.differences_only);
+                    } else {
+                        self.repair_form.ui(ui, attempt, repairs);
+                        ui.separator();
+                        report_table(ui, attempt, &self.filter);
+                    }
                 });
 
                 if ctx.input(|i| i.viewport().close_requested()) {
@@ -114,5 +463,45 @@ impl LogInfoWindow {
                 }
             },
         );
+
+        scanned
     }
 }
+
+fn attempt_label(i: usize, a: &Attempt) -> String {
+    format!(
+        "{}. {} - {}",
+        i + 1,
+        u64_to_datetime(a.time_start).map_or(String::new(), |t| t.format("%F %T").to_string()),
+        a.result.print()
+    )
+}
+
+fn attempt_selector(ui: &mut egui::Ui, id: &str, attempts: &[Attempt], selected: &mut usize) {
+    egui::ComboBox::from_id_source(id)
+        .width(250.0)
+        .selected_text(
+            attempts
+                .get(*selected)
+                .map_or(String::new(), |a| attempt_label(*selected, a)),
+        )
+        .show_ui(ui, |ui| {
+            for (i, a) in attempts.iter().enumerate() {
+                ui.selectable_value(selected, i, attempt_label(i, a));
+            }
+        });
+}
+
+fn attempt_header(ui: &mut egui::Ui, prefix: &str, attempt: &Attempt) {
+    let time = |t: u64| u64_to_datetime(t).map_or(String::new(), |t| t.format("%T").to_string());
+    ui.label(format!(
+        "{}{} (panel: {}) - index: {} - {} - {}",
+        prefix,
+        attempt.DMC,
+        attempt.DMC_mb,
+        attempt.index,
+        time(attempt.time_start),
+        time(attempt.time_end)
+    ))
+    .on_hover_text(attempt.source.to_string_lossy());
+}
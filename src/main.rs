@@ -21,14 +21,84 @@ use scan_dir::*;
 mod daily_yield;
 use daily_yield::*;
 
+mod shifts;
+use shifts::*;
+
+mod archive;
+use archive::*;
+
+mod spc;
+use spc::*;
+
+mod stats;
+use stats::*;
+
+mod histogram;
+use histogram::*;
+
+mod index_compare;
+use index_compare::*;
+
+mod export;
+use export::*;
+
+mod log_cache;
+use log_cache::*;
+
+mod multi_product;
+use multi_product::*;
+
+mod pareto;
+use pareto::*;
+
+mod trend;
+use trend::*;
+
+mod retest;
+use retest::*;
+
+mod wear;
+use wear::*;
+
+mod alarms;
+use alarms::*;
+
+mod publish;
+use publish::*;
+
+mod dmc_search;
+use dmc_search::*;
+
+mod board_report;
+use board_report::*;
+
+mod repairs;
+use repairs::*;
+
+mod scanner;
+use scanner::*;
+
+mod ticket;
+use ticket::*;
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use std::collections::HashMap;
 use std::fs;
+use std::io::Write;
 use std::ops::RangeInclusive;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, RwLock};
 use std::thread;
+use std::time::Instant;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 const PRODUCT_LIST: &str = ".\\products";
+const SHIFT_CONFIG: &str = ".\\shifts";
+const ALARM_CONFIG: &str = ".\\alarms";
+const PUBLISH_CONFIG: &str = ".\\publish";
+const REPAIR_LOG: &str = ".\\repairs.json";
+const HEADLESS_LOG: &str = ".\\daily_yield.log";
 include!("locals.rs");
 
 /*
@@ -57,10 +127,8 @@ fn get_logs_in_path(
 }
 
 fn is_dir_in_t(s: &Path, start: DateTime<Local>, end: DateTime<Local>) -> bool {
-    if let Ok(as_time) =
-        NaiveDate::parse_from_str(s.file_name().unwrap().to_str().unwrap(), "%Y_%m_%d")
-    {
-        if start.date_naive() <= as_time && end.date_naive() >= as_time {
+    if let Some((first, last)) = parse_archive_dir(s.file_name().unwrap().to_str().unwrap()) {
+        if start.date_naive() <= last && end.date_naive() >= first {
             return true;
         }
     }
@@ -92,17 +160,49 @@ fn get_logs_in_path_t(
     Ok(ret)
 }
 
-fn move_file_to_subdir(base_dir: &Path, subdir_name: String, file: &Path) -> std::io::Result<()> {
-    let new_dir = base_dir.join(subdir_name);
-    if !new_dir.exists() {
-        fs::create_dir(&new_dir)?;
-    }
-    let final_path = new_dir.join(file.file_name().unwrap());
-    println!("Moving {} to {}", file.display(), final_path.display());
+/*
+Parses the logs on every available core. Each worker takes the next unprocessed log,
+and collects the results in its own buffer, so there is no locking while parsing.
+The logs are returned in the original order. 'progress' is called after each log.
+*/
+fn parse_logs_parallel(
+    logs: &[(PathBuf, u64)],
+    cache: &LogCache,
+    progress: impl Fn() + Sync,
+) -> (Vec<LogFile>, Vec<CacheUpdate>) {
+    let workers = thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(logs.len().max(1));
+    let next = std::sync::atomic::AtomicUsize::new(0);
+
+    let results: Vec<Vec<(usize, LogFile, CacheUpdate)>> = thread::scope(|s| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                s.spawn(|| {
+                    let mut buffer = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                        let Some(log) = logs.get(i) else {
+                            break;
+                        };
+
+                        if let Some((logfile, update)) = cache.load(&log.0) {
+                            buffer.push((i, logfile, update));
+                        }
+                        progress();
+                    }
+                    buffer
+                })
+            })
+            .collect();
 
-    fs::rename(file, final_path)?;
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
 
-    Ok(())
+    let mut merged: Vec<(usize, LogFile, CacheUpdate)> = results.into_iter().flatten().collect();
+    merged.sort_by_key(|r| r.0);
+
+    merged.into_iter().map(|r| (r.1, r.2)).unzip()
 }
 
 // For AutoUpdater. Grabs files after time 't', but it will not scan subdirectories
@@ -113,7 +213,6 @@ fn get_logs_after_t(
     t: DateTime<Local>,
 ) -> Result<Vec<PathAndTime>, std::io::Error> {
     let mut ret: Vec<PathAndTime> = Vec::new();
-    let now = Local::now();
 
     for file in fs::read_dir(base_path)? {
         let file = file?;
@@ -123,9 +222,6 @@ fn get_logs_after_t(
                 let ct: DateTime<Local> = x.modified().unwrap().into();
                 if ct > t {
                     ret.push((path.to_path_buf(), ct));
-                } else if now - ct > Duration::try_hours(4).unwrap() {
-                    // if the log is older than 4 hours, then move it to a subdir
-                    move_file_to_subdir(base_path, format!("{}", ct.format("%Y_%m_%d")), &path)?;
                 }
             }
         }
@@ -136,6 +232,22 @@ fn get_logs_after_t(
     Ok(ret)
 }
 
+// YYMMDDHHMMSS format u64 to date and time
+fn u64_to_datetime(mut x: u64) -> Option<NaiveDateTime> {
+    let s = (x % 100) as u32;
+    x /= 100;
+    let min = (x % 100) as u32;
+    x /= 100;
+    let h = (x % 100) as u32;
+    x /= 100;
+    let d = (x % 100) as u32;
+    x /= 100;
+    let m = (x % 100) as u32;
+    x /= 100;
+
+    NaiveDate::from_ymd_opt(2000 + x as i32, m, d)?.and_hms_opt(h, min, s)
+}
+
 // Turn YYMMDDHH format u64 int to "YY.MM.DD HH:00 - HH:59"
 fn u64_to_timeframe(mut x: u64) -> String {
     let y = x / u64::pow(10, 6);
@@ -153,9 +265,71 @@ fn u64_to_timeframe(mut x: u64) -> String {
     )
 }
 
+// Release builds have no console, so the output of the headless mode is also appended to HEADLESS_LOG
+fn headless_output(msg: &str) {
+    print!("{msg}");
+
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(HEADLESS_LOG);
+    if let Ok(mut file) = file {
+        let _ = file.write_all(msg.as_bytes());
+    }
+}
+
+// Headless mode, for running the daily report from a scheduled job:
+// ICT_log_parser --daily-yield <YYYY-MM-DD|yesterday> <output.xlsx> [products file]
+// With a .csv or .jsonl output, the measurements of the day are exported there, next to the xlsx report.
+// Returns the exit code, nonzero if the report could not be generated.
+fn run_headless(args: &[String]) -> i32 {
+    headless_output(&format!(
+        "INFO: {} {}\n",
+        Local::now().format("%F %T"),
+        args.join(" ")
+    ));
+
+    if !(3..=4).contains(&args.len()) {
+        headless_output("Usage: ICT_log_parser --daily-yield <YYYY-MM-DD|yesterday> <output.xlsx> [products file]\n");
+        return 1;
+    }
+
+    let date = if args[1] == "yesterday" {
+        Local::now().date_naive().pred_opt().unwrap()
+    } else {
+        match NaiveDate::parse_from_str(&args[1], "%Y-%m-%d") {
+            Ok(x) => x,
+            Err(_) => {
+                headless_output(&format!("ERR: Failed to parse date: {}\n", args[1]));
+                return 1;
+            }
+        }
+    };
+
+    let product_list = load_product_list(args.get(3).map_or(PRODUCT_LIST, |x| x.as_str()));
+    let path_list: Vec<PathBuf> = product_list.iter().map(|f| f.get_log_dir().clone()).collect();
+    let shift_model = ShiftModel::load(SHIFT_CONFIG);
+
+    let long_format = match ExportFormat::from_path(Path::new(&args[2])) {
+        ExportFormat::Xlsx => None,
+        x => Some(x),
+    };
+
+    if generate_daily_report(date, &path_list, &shift_model, &args[2], long_format, headless_output) {
+        0
+    } else {
+        1
+    }
+}
+
 fn main() -> Result<(), eframe::Error> {
     env_logger::init(); // Log to stderr (if you run with `RUST_LOG=debug`).
 
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("--daily-yield") {
+        std::process::exit(run_headless(&args[1..]));
+    }
+
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default().with_inner_size(Vec2 { x: 830.0, y: 450.0 }),
         ..Default::default()
@@ -178,6 +352,20 @@ enum AppMode {
     Hourly,
     Multiboards,
     Export,
+    Pareto,
+    Trend,
+    Retest,
+    Wear,
+    Alarms,
+    Repairs,
+}
+
+#[derive(PartialEq)]
+enum PlotView {
+    TimeSeries,
+    Histogram,
+    ByIndex,
+    Spc,
 }
 
 #[derive(PartialEq)]
@@ -187,7 +375,7 @@ enum YieldMode {
 }
 enum LoadMode {
     Folder(PathBuf),
-    ProductList(PathBuf),
+    ProductList(Vec<usize>),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -202,16 +390,31 @@ struct AutoUpdate {
     enabled: bool,
     state: Arc<RwLock<AUState>>,
 
-    product: Option<usize>,
+    products: Vec<ProductHandler>,
     last_log: Option<DateTime<Local>>,
     update_start_time: Option<DateTime<Local>>,
     last_scan_time: Option<DateTime<Local>>,
 
     log_buffer: Arc<RwLock<Vec<PathAndTime>>>,
+
+    // If there is a watcher, then new logs are collected from its events.
+    // The directories are still polled every 30s, in case the watcher misses some events.
+    watcher: Option<RecommendedWatcher>,
+    pending_logs: Arc<RwLock<HashMap<PathBuf, Instant>>>,
+    rescan: Arc<RwLock<bool>>, // the watcher failed or lost events, poll as soon as possible
+    polling: bool,             // the current update is a directory scan
+    pushed_logs: HashMap<PathBuf, DateTime<Local>>, // logs already pushed, and their modification time
 }
 
+// Logs are only pushed after no new events came for them for this long, so the tester can finish writing them.
+const LOG_DEBOUNCE: std::time::Duration = std::time::Duration::from_secs(2);
+// Pushed logs are remembered for this long, so later events or scans don't push them again
+const PUSHED_LOG_MEMORY: i64 = 24;
+
 /*
  Standby -> its_time --Loading--> gather_logs --Loaded--> push_logs -> Standby
+ With a watcher gather_logs goes directly to Loaded, with the debounced logs from pending_logs,
+ unless a poll is due.
 */
 
 impl AutoUpdate {
@@ -220,12 +423,18 @@ impl AutoUpdate {
             usable: false,
             enabled: false,
             state: Arc::new(RwLock::new(AUState::Standby)),
-            product: None,
+            products: Vec::new(),
             last_log: None,
             update_start_time: None,
             last_scan_time: None,
 
             log_buffer: Arc::new(RwLock::new(Vec::new())),
+
+            watcher: None,
+            pending_logs: Arc::new(RwLock::new(HashMap::new())),
+            rescan: Arc::new(RwLock::new(false)),
+            polling: false,
+            pushed_logs: HashMap::new(),
         }
     }
 
@@ -233,75 +442,210 @@ impl AutoUpdate {
         self.usable = false;
         self.enabled = false;
         self.state = Arc::new(RwLock::new(AUState::Standby));
-        self.product = None;
+        self.products.clear();
         self.last_log = None;
         self.update_start_time = None;
         self.last_scan_time = None;
         self.log_buffer.write().unwrap().clear();
+        self.watcher = None;
+        self.pending_logs.write().unwrap().clear();
+        *self.rescan.write().unwrap() = false;
+        self.polling = false;
+        self.pushed_logs.clear();
+    }
+
+    // Starts watching the log directories. On failure, or for network shares it stays in polling mode.
+    // Mapped network drives can't be told apart here, those are covered by the 30s poll.
+    fn start_watcher(&mut self, paths: &[PathBuf], ctx: &egui::Context) {
+        if let Some(path) = paths
+            .iter()
+            .find(|p| p.to_string_lossy().starts_with("\\\\"))
+        {
+            println!("INFO: {} is a network share, using polling.", path.display());
+            return;
+        }
+
+        let pending_lock = self.pending_logs.clone();
+        let rescan_lock = self.rescan.clone();
+        let frame = ctx.clone();
+
+        let watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+            match res {
+                Ok(event) if event.need_rescan() => {
+                    println!("INFO: The watcher lost events, scanning the directories.");
+                    *rescan_lock.write().unwrap() = true;
+                    frame.request_repaint();
+                }
+                Ok(event) if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) => {
+                    let mut pending = pending_lock.write().unwrap();
+                    for path in event.paths {
+                        if path.extension().is_none() {
+                            pending.insert(path, Instant::now());
+                        }
+                    }
+
+                    frame.request_repaint_after(LOG_DEBOUNCE + std::time::Duration::from_millis(100));
+                }
+                Ok(_) => (),
+                Err(e) => {
+                    println!("ERR: Watcher error, scanning the directories: {e}");
+                    *rescan_lock.write().unwrap() = true;
+                    frame.request_repaint();
+                }
+            }
+        })
+        .and_then(|mut w| {
+            for path in paths {
+                w.watch(path, RecursiveMode::NonRecursive)?;
+            }
+            Ok(w)
+        });
+
+        match watcher {
+            Ok(w) => {
+                println!("INFO: Watching {:?} for new logs.", paths);
+                self.watcher = Some(w);
+            }
+            Err(e) => {
+                println!("ERR: Failed to watch {:?}, using polling: {e}", paths);
+                self.watcher = None;
+            }
+        }
     }
 
     fn state(&self) -> AUState {
         *self.state.read().unwrap()
     }
 
+    fn poll_due(&self) -> bool {
+        if *self.rescan.read().unwrap() {
+            return true;
+        }
+
+        if let Some(t) = self.last_scan_time {
+            return (Local::now() - t).num_seconds() > 30;
+        }
+
+        false
+    }
+
     fn its_time(&self) -> bool {
         if self.enabled && *self.state.read().unwrap() == AUState::Standby {
-            if let Some(t) = self.last_scan_time {
-                return (Local::now() - t).num_seconds() > 30;
+            if self.watcher.is_some()
+                && self
+                    .pending_logs
+                    .read()
+                    .unwrap()
+                    .values()
+                    .any(|t| t.elapsed() >= LOG_DEBOUNCE)
+            {
+                return true;
             }
+
+            return self.poll_due();
         }
 
         false
     }
 
-    fn gather_logs(&mut self, products: &[Product]) {
-        if let Some(prod) =
-            products.get(self.product.expect("ERR: Auto Updater has no product ID!"))
-        {
+    fn gather_logs(&mut self) {
+        self.polling = self.watcher.is_none() || self.poll_due();
+        *self.rescan.write().unwrap() = false;
+
+        if !self.polling {
             self.update_start_time = Some(Local::now());
-            let state_lock = self.state.clone();
-            let log_lock = self.log_buffer.clone();
-            let path = prod.get_log_dir().clone();
-
-            // ToDo:
-            // Idealy we would get last-log from the last manual load.
-            // That would need the re-write of the fn.
-            let start = if let Some(x) = self.last_log {
-                x - Duration::try_seconds(5).unwrap()
-            } else {
-                self.last_scan_time.unwrap() - Duration::try_minutes(5).unwrap()
-            };
 
-            thread::spawn(move || {
-                *state_lock.write().unwrap() = AUState::Loading;
-                if let Ok(logs) = get_logs_after_t(&path, start) {
-                    *log_lock.write().unwrap() = logs;
+            let mut pending = self.pending_logs.write().unwrap();
+            let ready: Vec<PathBuf> = pending
+                .iter()
+                .filter(|(_, t)| t.elapsed() >= LOG_DEBOUNCE)
+                .map(|(p, _)| p.clone())
+                .collect();
+
+            let mut logs: Vec<PathAndTime> = Vec::new();
+            for path in ready {
+                pending.remove(&path);
+                if let Ok(x) = path.metadata() {
+                    if x.is_file() {
+                        logs.push((path, x.modified().unwrap().into()));
+                    }
                 }
+            }
+            logs.sort_by_key(|k| k.1);
 
-                *state_lock.write().unwrap() = AUState::Loaded;
-            });
+            *self.log_buffer.write().unwrap() = logs;
+            *self.state.write().unwrap() = AUState::Loaded;
+            return;
         }
+
+        if self.products.is_empty() {
+            panic!("ERR: Auto Updater has no products!");
+        }
+
+        self.update_start_time = Some(Local::now());
+        let state_lock = self.state.clone();
+        let log_lock = self.log_buffer.clone();
+        let paths: Vec<PathBuf> = self.products.iter().map(|p| p.0.clone()).collect();
+
+        // ToDo:
+        // Idealy we would get last-log from the last manual load.
+        // That would need the re-write of the fn.
+        let start = if let Some(x) = self.last_log {
+            x - Duration::try_seconds(5).unwrap()
+        } else {
+            self.last_scan_time.unwrap() - Duration::try_minutes(5).unwrap()
+        };
+
+        thread::spawn(move || {
+            *state_lock.write().unwrap() = AUState::Loading;
+            let mut logs: Vec<PathAndTime> = Vec::new();
+            for path in paths {
+                if let Ok(mut x) = get_logs_after_t(&path, start) {
+                    logs.append(&mut x);
+                }
+            }
+            logs.sort_by_key(|k| k.1);
+            *log_lock.write().unwrap() = logs;
+
+            *state_lock.write().unwrap() = AUState::Loaded;
+        });
     }
 
-    fn push_logs(&mut self, lfh: Arc<RwLock<LogFileHandler>>) -> (Duration, usize) {
+    fn push_logs(&mut self) -> (Duration, usize) {
         if self.state() != AUState::Loaded {
             panic!("ERR: AutoUpdate -> Push logs called at wrong time!");
         }
 
         let mut new_logs: usize = 0;
+        let pending = self.pending_logs.read().unwrap();
 
+        // Each log goes to the handler of the directory it is in.
+        // Logs pushed before are skipped, and so are the ones the watcher still waits for.
         for log in self.log_buffer.read().unwrap().iter() {
-            if lfh.write().unwrap().push_from_file(&log.0) {
-                new_logs += 1;
+            if self.pushed_logs.contains_key(&log.0) || pending.contains_key(&log.0) {
+                continue;
+            }
+
+            if let Some((_, lfh)) = self.products.iter().find(|p| log.0.starts_with(&p.0)) {
+                if lfh.write().unwrap().push_from_file(&log.0) {
+                    new_logs += 1;
+                }
+                self.pushed_logs.insert(log.0.clone(), log.1);
             }
         }
+        drop(pending);
 
         if let Some((_, x)) = self.log_buffer.read().unwrap().last() {
-            self.last_log = Some(*x);
+            self.last_log = Some(self.last_log.map_or(*x, |l| l.max(*x)));
         }
 
+        let forget = Local::now() - Duration::try_hours(PUSHED_LOG_MEMORY).unwrap();
+        self.pushed_logs.retain(|_, t| *t > forget);
+
         self.log_buffer.write().unwrap().clear();
-        self.last_scan_time = Some(Local::now());
+        if self.polling {
+            self.last_scan_time = Some(Local::now());
+        }
         *self.state.write().unwrap() = AUState::Standby;
         let update_time = Local::now() - self.update_start_time.unwrap();
 
@@ -314,8 +658,11 @@ struct MyApp {
     status: String,
     lang: usize,
     selected_product: usize,
+    combined_products: Vec<bool>, // loaded together with the selected product
     product_list: Vec<Product>,
-    log_master: Arc<RwLock<LogFileHandler>>,
+    loaded_products: Vec<LoadedProduct>,
+    product_filter: Option<usize>, // None = every loaded product
+    log_master: Arc<RwLock<LogFileHandler>>, // handler of the filtered product, or the first one
 
     date_start: NaiveDate,
     date_end: NaiveDate,
@@ -326,7 +673,12 @@ struct MyApp {
     time_end_string: String,
     time_end_use: bool,
 
+    shift_model: ShiftModel,
     auto_update: AutoUpdate,
+    alarms: AlarmMonitor,
+    publisher: Publisher,
+    repairs: RepairLog,
+    scanner: BarcodeScanner,
 
     loading: bool,
     progress_x: Arc<RwLock<u32>>,
@@ -335,8 +687,9 @@ struct MyApp {
     yield_mode: YieldMode,
     yields: [Yield; 3],
     mb_yields: [Yield; 3],
+    product_yields: Vec<([Yield; 3], [Yield; 3])>,
     fl_setting: FlSettings,
-    failures: Vec<FailureList>,
+    failures: Vec<(usize, FailureList)>, // (product, failure)
     limitchanges: Option<Vec<(usize, String)>>,
 
     mode: AppMode,
@@ -348,12 +701,22 @@ struct MyApp {
     selected_test_buf: String,
     selected_test_index: usize,
     selected_test_results: (TType, Vec<(u64, usize, TResult, TLimit)>),
+    selected_test_stats: Option<(usize, Option<TestStatistics>)>, // (index, statistics) of the loaded results
+    plot_view: PlotView,
+    histogram_view: HistogramView,
+    spc_view: SpcView,
+    pareto_view: ParetoView,
+    trend_view: TrendView,
+    retest_view: RetestView,
+    wear_view: WearView,
 
     export_settings: ExportSettings,
+    export_format: ExportFormat,
 
     info_vp: LogInfoWindow,
     scan_vp: ScanDirWindow,
     daily_yield_vp: DailyYieldWindow,
+    archive_vp: ArchiveWindow,
 }
 
 impl Default for MyApp {
@@ -366,12 +729,20 @@ impl Default for MyApp {
         println!("{:?}", product_list);
         
         let path_list: Vec<PathBuf> = product_list.iter().map(|f| f.get_log_dir().clone()).collect();
+        let shift_model = ShiftModel::load(SHIFT_CONFIG);
+        let archive_list: Vec<(String, PathBuf)> = product_list
+            .iter()
+            .map(|f| (f.get_name().to_string(), f.get_log_dir().clone()))
+            .collect();
 
         Self {
             status: "".to_owned(),
             lang: 0,
+            combined_products: vec![false; product_list.len()],
             product_list,
             selected_product: 0,
+            loaded_products: Vec::new(),
+            product_filter: None,
             log_master: Arc::new(RwLock::new(LogFileHandler::new())),
 
             date_start: Local::now().date_naive(),
@@ -383,7 +754,12 @@ impl Default for MyApp {
             time_end_string: time_end.format("%H:%M:%S").to_string(),
             time_end_use: false,
 
+            shift_model: shift_model.clone(),
             auto_update: AutoUpdate::default(),
+            alarms: AlarmMonitor::new(AlarmConfig::load(ALARM_CONFIG)),
+            publisher: Publisher::load(PUBLISH_CONFIG),
+            repairs: RepairLog::load(REPAIR_LOG),
+            scanner: BarcodeScanner::default(),
 
             loading: false,
             progress_x: Arc::new(RwLock::new(0)),
@@ -392,6 +768,7 @@ impl Default for MyApp {
             yield_mode: YieldMode::SingleBoard,
             yields: [Yield(0, 0), Yield(0, 0), Yield(0, 0)],
             mb_yields: [Yield(0, 0), Yield(0, 0), Yield(0, 0)],
+            product_yields: Vec::new(),
             fl_setting: FlSettings::AfterRetest,
             failures: Vec::new(),
             limitchanges: None,
@@ -404,48 +781,187 @@ impl Default for MyApp {
             selected_test_buf: String::new(),
             selected_test_index: 0,
             selected_test_results: (TType::Unknown, Vec::new()),
+            selected_test_stats: None,
+            plot_view: PlotView::TimeSeries,
+            histogram_view: HistogramView::default(),
+            spc_view: SpcView::default(),
+            pareto_view: ParetoView::default(),
+            trend_view: TrendView::default(),
+            retest_view: RetestView::default(),
+            wear_view: WearView::default(),
 
             export_settings: ExportSettings::default(),
-            info_vp: LogInfoWindow::default(),
+            export_format: ExportFormat::Xlsx,
+            info_vp: LogInfoWindow::default(archive_list.clone()),
             scan_vp: ScanDirWindow::default(),
-            daily_yield_vp: DailyYieldWindow::default(path_list),
+            daily_yield_vp: DailyYieldWindow::default(path_list, shift_model),
+            archive_vp: ArchiveWindow::default(archive_list),
         }
     }
 }
 
 impl MyApp {
     fn update_stats(&mut self, ctx: &egui::Context) {
-        let mut lock = self.log_master.write().unwrap();
+        for product in &self.loaded_products {
+            product.lfh.write().unwrap().update();
+        }
+
+        self.retest_view.reset();
+        self.wear_view.reset();
+        self.update_view();
+
+        if self.auto_update.enabled {
+            self.alarms.check_logs(&self.loaded_products);
+        }
+        self.publisher.publish(&self.loaded_products);
 
-        lock.update();
-        self.yields = lock.get_yields();
-        self.mb_yields = lock.get_mb_yields();
-        self.failures = lock.get_failures(self.fl_setting);
-        self.hourly_stats = lock.get_hourly_mb_stats();
-        self.multiboard_results = lock.get_mb_results();
-        self.limitchanges = lock.get_tests_w_limit_changes();
+        let handlers: Vec<_> = self.loaded_products.iter().map(|p| p.lfh.clone()).collect();
+        self.repairs.link_retests(&handlers);
 
         ctx.request_repaint();
     }
 
+    // Gathers the statistics of the products selected by the product filter
+    fn update_view(&mut self) {
+        if self.loaded_products.is_empty() {
+            return;
+        }
+
+        let selected: Vec<usize> = match self.product_filter {
+            Some(i) => vec![i],
+            None => (0..self.loaded_products.len()).collect(),
+        };
+        // Collected before the handlers below are locked, as taking the same read lock twice
+        // can deadlock, if a writer is already waiting for it.
+        self.product_yields = self
+            .loaded_products
+            .iter()
+            .map(|p| {
+                let lock = p.lfh.read().unwrap();
+                (lock.get_yields(), lock.get_mb_yields())
+            })
+            .collect();
+
+        let handlers: Vec<(usize, std::sync::RwLockReadGuard<LogFileHandler>)> = selected
+            .iter()
+            .map(|i| (*i, self.loaded_products[*i].lfh.read().unwrap()))
+            .collect();
+        self.yields = sum_yields(handlers.iter().map(|h| h.1.get_yields()));
+        self.mb_yields = sum_yields(handlers.iter().map(|h| h.1.get_mb_yields()));
+        self.failures = combine_failures(
+            handlers
+                .iter()
+                .map(|h| (h.0, h.1.get_failures(self.fl_setting)))
+                .collect(),
+        );
+        self.hourly_stats =
+            combine_hourly(handlers.iter().map(|h| h.1.get_hourly_mb_stats()).collect());
+        self.multiboard_results =
+            combine_mb_results(handlers.iter().map(|h| h.1.get_mb_results()).collect());
+        drop(handlers);
+
+        self.log_master = self.loaded_products[self.product_filter.unwrap_or(0)]
+            .lfh
+            .clone();
+        self.limitchanges = self.log_master.read().unwrap().get_tests_w_limit_changes();
+    }
+
+    // More than one product is loaded, and the filter shows all of them
+    fn is_combined(&self) -> bool {
+        self.product_filter.is_none() && self.loaded_products.len() > 1
+    }
+
+    // Opens the plot of a failing test. In the combined view, it also switches to its product.
+    fn select_failure(&mut self, product: usize, name: String) {
+        if self.is_combined() {
+            self.set_product_filter(Some(product));
+        }
+        self.selected_test_buf = name;
+        self.mode = AppMode::Plot;
+    }
+
+    fn set_product_filter(&mut self, filter: Option<usize>) {
+        self.product_filter = filter;
+        self.selected_test = 0;
+        self.selected_test_results.1.clear();
+        self.retest_view.reset();
+        self.wear_view.reset();
+        self.update_view();
+    }
+
+    // Handlers of the products selected by the product filter
+    fn filtered_handlers(&self) -> Vec<Arc<RwLock<LogFileHandler>>> {
+        if self.is_combined() {
+            self.loaded_products.iter().map(|p| p.lfh.clone()).collect()
+        } else {
+            vec![self.log_master.clone()]
+        }
+    }
+
+    // Opens the report of a scanned DMC from the loaded logs, or searches it on the disk
+    fn open_scanned(&mut self, DMC: String) {
+        println!("INFO: Scanned DMC: {DMC}");
+
+        let lfh = self
+            .loaded_products
+            .iter()
+            .map(|p| p.lfh.clone())
+            .find(|l| l.read().unwrap().get_report_for_SB(&DMC).is_some());
+
+        // A multiboard label: the panel opens on its first failed board
+        let panel_lfh = || {
+            self.loaded_products
+                .iter()
+                .map(|p| p.lfh.clone())
+                .find(|l| {
+                    l.read()
+                        .unwrap()
+                        .get_logs()
+                        .iter()
+                        .any(|log| log.get_DMC_mb() == DMC)
+                })
+        };
+
+        if let Some(lfh) = lfh {
+            self.info_vp.open(DMC, lfh);
+        } else if let Some(lfh) = panel_lfh() {
+            self.info_vp.open_first_NOK(DMC, lfh);
+        } else {
+            self.info_vp.open_global(DMC);
+        }
+        self.info_vp.show_verdict();
+    }
+
     // Do I even need to clear these?
     fn clear_stats(&mut self) {
         self.hourly_stats.clear();
         self.multiboard_results.clear();
         self.auto_update.clear();
+        self.alarms.clear();
+        self.publisher.clear();
+        self.trend_view.reset();
         self.selected_test = 0;
         *self.progress_x.write().unwrap() = 0;
         *self.progress_m.write().unwrap() = 1;
     }
 
     fn load_logs(&mut self, ctx: &egui::Context, mode: LoadMode) {
-        //let input_path = product.path.clone();
-
-        let input_path = match mode {
-            LoadMode::Folder(ref x) => x.clone(),
-            LoadMode::ProductList(ref x) => PathBuf::from(x),
+        let products: Vec<LoadedProduct> = match mode {
+            LoadMode::Folder(ref x) => vec![LoadedProduct::new(
+                &x.file_name().unwrap_or_default().to_string_lossy(),
+                x.clone(),
+            )],
+            LoadMode::ProductList(ref list) => list
+                .iter()
+                .filter_map(|i| self.product_list.get(*i))
+                .map(|p| LoadedProduct::new(p.get_name(), p.get_log_dir().clone()))
+                .collect(),
         };
 
+        if products.is_empty() {
+            return;
+        }
+
         let start_dt = TimeZone::from_local_datetime(
             &Local,
             &NaiveDateTime::new(self.date_start, self.time_start),
@@ -467,37 +983,71 @@ impl MyApp {
         self.loading = true;
         self.clear_stats();
 
+        let jobs: Vec<ProductHandler> = products
+            .iter()
+            .map(|p| (p.log_dir.clone(), p.lfh.clone()))
+            .collect();
+        self.log_master = products[0].lfh.clone();
+        self.loaded_products = products;
+        self.product_filter = None;
+
         if matches!(mode, LoadMode::ProductList(_)) && !self.time_end_use {
+            let paths: Vec<PathBuf> = jobs.iter().map(|j| j.0.clone()).collect();
             self.auto_update.enabled = true;
             self.auto_update.usable = true;
-            self.auto_update.product = Some(self.selected_product);
+            self.auto_update.products = jobs.clone();
             self.auto_update.last_scan_time = Some(Local::now());
+            self.auto_update.start_watcher(&paths, ctx);
         }
 
-        let lb_lock = self.log_master.clone();
         let pm_lock = self.progress_m.clone();
         let px_lock = self.progress_x.clone();
         let frame = ctx.clone();
 
         thread::spawn(move || {
-            let logs_result = match mode {
-                LoadMode::Folder(_) => get_logs_in_path(&input_path, pm_lock.clone()),
-                LoadMode::ProductList(_) => get_logs_in_path_t(&input_path, start_dt, end_dt),
-            };
+            let mut found: Vec<(ProductHandler, Vec<(PathBuf, u64)>)> = Vec::new();
 
-            if let Ok(mut logs) = logs_result {
-                *pm_lock.write().unwrap() = logs.len() as u32;
-                (*lb_lock.write().unwrap()).clear();
-                frame.request_repaint_after(std::time::Duration::from_millis(500));
+            for (input_path, lfh) in jobs {
+                let logs_result = match mode {
+                    LoadMode::Folder(_) => get_logs_in_path(&input_path, pm_lock.clone()),
+                    LoadMode::ProductList(_) => get_logs_in_path_t(&input_path, start_dt, end_dt),
+                };
 
-                println!("Found {} logs to load.", logs.len());
-                logs.sort_by_key(|k| k.1);
+                match logs_result {
+                    Ok(mut logs) => {
+                        println!("Found {} logs to load in {}.", logs.len(), input_path.display());
+                        logs.sort_by_key(|k| k.1);
+                        logs.reverse();
+                        found.push(((input_path, lfh), logs));
+                    }
+                    Err(e) => println!("ERR: Failed to read {}: {e}", input_path.display()),
+                }
+            }
+
+            // +1 for each merge, so the UI doesn't finish loading before them.
+            *pm_lock.write().unwrap() =
+                found.iter().map(|f| f.1.len() as u32 + 1).sum::<u32>();
+            frame.request_repaint_after(std::time::Duration::from_millis(500));
 
-                for log in logs.iter().rev() {
-                    (*lb_lock.write().unwrap()).push_from_file(&log.0);
+            for ((input_path, lb_lock), logs) in found {
+                // Unchanged logs are read from the local cache, only the new ones are parsed.
+                let mut cache = LogCache::open(&input_path);
+                let (parsed, updates) = parse_logs_parallel(&logs, &cache, || {
                     *px_lock.write().unwrap() += 1;
                     frame.request_repaint_after(std::time::Duration::from_millis(500));
+                });
+
+                {
+                    let mut lfh = lb_lock.write().unwrap();
+                    for log in parsed {
+                        lfh.push(log);
+                    }
                 }
+                *px_lock.write().unwrap() += 1;
+                frame.request_repaint();
+
+                cache.apply(updates);
+                cache.save();
             }
         });
     }
@@ -507,6 +1057,14 @@ impl eframe::App for MyApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         ctx.request_repaint_after(std::time::Duration::from_secs(5));
 
+        if self.alarms.has_active() && alarm_banner(ctx, &mut self.alarms, self.lang) {
+            self.mode = AppMode::Alarms;
+        }
+
+        if let Some(DMC) = self.scanner.feed(ctx) {
+            self.open_scanned(DMC);
+        }
+
         egui::SidePanel::left("Settings_panel").show(ctx, |ui| {
             ui.set_min_width(270.0);
 
@@ -531,6 +1089,23 @@ impl eframe::App for MyApp {
                             ui.selectable_value(&mut self.selected_product, i, t.get_name().to_string());
                         }
                     });
+
+                // Other products to load together with the selected one
+                let combined = self
+                    .combined_products
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, x)| **x && *i != self.selected_product)
+                    .count();
+                let label = if combined > 0 { format!("+{combined}") } else { "+".to_string() };
+                ui.menu_button(label, |ui| {
+                    ui.label(MESSAGE[COMBINED][self.lang]);
+                    for (i, t) in self.product_list.iter().enumerate() {
+                        if i != self.selected_product {
+                            ui.checkbox(&mut self.combined_products[i], t.get_name());
+                        }
+                    }
+                });
             });
 
             ui.separator();
@@ -559,25 +1134,14 @@ impl eframe::App for MyApp {
 
                 // Set timeframe to this shift
                 if ui.button(MESSAGE[SHIFT][self.lang]).clicked() {
-                    self.date_start = Local::now().date_naive();
-                    self.date_end = Local::now().date_naive();
-
-                    let time_now = Local::now().naive_local();
-                    let hours_now = time_now.hour();
-                    if (6..14).contains(&hours_now) {
-                        self.time_start = NaiveTime::from_hms_opt(6, 0, 0).unwrap();
-                        self.time_end = NaiveTime::from_hms_opt(13, 59, 59).unwrap();
-                    } else if (14..22).contains(&hours_now) {
-                        self.time_start = NaiveTime::from_hms_opt(14, 0, 0).unwrap();
-                        self.time_end = NaiveTime::from_hms_opt(21, 59, 59).unwrap();
-                    } else {
-                        if hours_now < 6 {
-                            self.date_start = self.date_start.pred_opt().unwrap();
-                        } else {
-                            self.date_end = self.date_end.succ_opt().unwrap();
-                        }
-                        self.time_start = NaiveTime::from_hms_opt(22, 0, 0).unwrap();
-                        self.time_end = NaiveTime::from_hms_opt(5, 59, 59).unwrap();
+                    if let Some((start, end)) =
+                        self.shift_model.current_shift(Local::now().naive_local())
+                    {
+                        let end = end - Duration::try_seconds(1).unwrap();
+                        self.date_start = start.date();
+                        self.time_start = start.time();
+                        self.date_end = end.date();
+                        self.time_end = end.time();
                     }
 
                     self.time_start_string = self.time_start.format("%H:%M:%S").to_string();
@@ -624,9 +1188,13 @@ impl eframe::App for MyApp {
                 ui.add(egui::Checkbox::without_text(&mut self.time_end_use));
 
                 if ui.button(MESSAGE[LOAD][self.lang]).clicked() && !self.loading {
-                    if let Some(product) = self.product_list.get(self.selected_product) {
-                        self.load_logs(ctx, LoadMode::ProductList(product.get_log_dir().clone()));
+                    let mut products = vec![self.selected_product];
+                    for (i, x) in self.combined_products.iter().enumerate() {
+                        if *x && i != self.selected_product {
+                            products.push(i);
+                        }
                     }
+                    self.load_logs(ctx, LoadMode::ProductList(products));
                 }
             });
 
@@ -670,15 +1238,16 @@ impl eframe::App for MyApp {
                     self.update_stats(ctx);
                 }
             } else if self.auto_update.enabled {
+                self.alarms.check_idle();
+
                 match self.auto_update.state() {
                     AUState::Standby => {
                         if self.auto_update.its_time() {
-                            self.auto_update.gather_logs(&self.product_list);
+                            self.auto_update.gather_logs();
                         }
                     }
                     AUState::Loaded => {
-                        let (duration, number) =
-                            self.auto_update.push_logs(self.log_master.clone());
+                        let (duration, number) = self.auto_update.push_logs();
 
                         self.status = format!(
                             "{}{}{}{}",
@@ -748,6 +1317,55 @@ impl eframe::App for MyApp {
                 });
             });
 
+            // Product filter, and the yields of each loaded product
+            if self.loaded_products.len() > 1 {
+                ui.separator();
+
+                let mut filter = self.product_filter;
+                ui.horizontal(|ui| {
+                    ui.monospace(MESSAGE[PRODUCT_FILTER][self.lang]);
+                    egui::ComboBox::from_id_source("Product filter")
+                        .selected_text(match filter {
+                            Some(i) => self.loaded_products[i].name.clone(),
+                            None => MESSAGE[ALL_PRODUCTS][self.lang].to_string(),
+                        })
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut filter, None, MESSAGE[ALL_PRODUCTS][self.lang]);
+                            for (i, p) in self.loaded_products.iter().enumerate() {
+                                ui.selectable_value(&mut filter, Some(i), p.name.clone());
+                            }
+                        });
+                });
+
+                egui::Grid::new("product yields").striped(true).show(ui, |ui| {
+                    ui.monospace("");
+                    ui.monospace(MESSAGE[FIRST_T][self.lang]);
+                    ui.monospace(MESSAGE[AFTER_RT][self.lang]);
+                    ui.end_row();
+
+                    for (i, (p, y)) in self.loaded_products.iter().zip(&self.product_yields).enumerate() {
+                        let x = match self.yield_mode {
+                            YieldMode::SingleBoard => &y.0,
+                            YieldMode::MultiBoard => &y.1,
+                        };
+
+                        if ui
+                            .selectable_label(filter == Some(i), p.name.clone())
+                            .clicked()
+                        {
+                            filter = Some(i);
+                        }
+                        ui.monospace(format!("{0:.2}%", x[0].precentage()));
+                        ui.monospace(format!("{0:.2}%", x[1].precentage()));
+                        ui.end_row();
+                    }
+                });
+
+                if filter != self.product_filter {
+                    self.set_product_filter(filter);
+                }
+            }
+
             // Failure list:
 
             ui.vertical(|ui| {
@@ -786,20 +1404,24 @@ impl eframe::App for MyApp {
                     });
                 if fl_change {
                     println!("reloading tests with mode {:?}", self.fl_setting);
-                    self.failures = self
-                        .log_master
-                        .read()
-                        .unwrap()
-                        .get_failures(self.fl_setting);
+                    self.update_view();
                 }
 
+                // With multiple products, there is a product column, and clicking a test also sets the product filter
+                let combined = self.is_combined();
+                let mut clicked: Option<(usize, String)> = None;
+
                 if !self.failures.is_empty() {
                     TableBuilder::new(ui)
                         .striped(true)
                         .column(Column::initial(220.0).resizable(true))
+                        .columns(
+                            Column::initial(80.0).resizable(true).clip(true),
+                            if combined { 1 } else { 0 },
+                        )
                         .column(Column::remainder())
                         .body(|mut body| {
-                            for fail in &self.failures {
+                            for (product, fail) in &self.failures {
                                 body.row(16.0, |mut row| {
                                     row.col(|ui| {
                                         if ui
@@ -810,10 +1432,14 @@ impl eframe::App for MyApp {
                                             )
                                             .clicked()
                                         {
-                                            self.selected_test_buf = fail.name.clone();
-                                            self.mode = AppMode::Plot;
+                                            clicked = Some((*product, fail.name.clone()));
                                         }
                                     });
+                                    if combined {
+                                        row.col(|ui| {
+                                            ui.label(&self.loaded_products[*product].name);
+                                        });
+                                    }
                                     row.col(|ui| {
                                         ui.label(format!("{}", fail.total));
                                     });
@@ -821,6 +1447,10 @@ impl eframe::App for MyApp {
                             }
                         });
                 }
+
+                if let Some((product, name)) = clicked {
+                    self.select_failure(product, name);
+                }
             });
         });
 
@@ -848,11 +1478,11 @@ impl eframe::App for MyApp {
         });
 
         // Failed DMC list for Plot view - needs its own panel!
-        if self.mode == AppMode::Plot && !self.failures.is_empty() {
-            if let Some(x) = self
+        if self.mode == AppMode::Plot && !self.failures.is_empty() && !self.is_combined() {
+            if let Some((_, x)) = self
                 .failures
                 .iter()
-                .find(|k| k.test_id == self.selected_test)
+                .find(|k| k.1.test_id == self.selected_test)
             {
                 egui::TopBottomPanel::bottom("failed panels")
                     .resizable(true)
@@ -864,6 +1494,7 @@ impl eframe::App for MyApp {
                                 .striped(true)
                                 .column(Column::auto())
                                 .column(Column::auto())
+                                .column(Column::auto())
                                 .body(|mut body| {
                                     for fail in &x.failed {
                                         body.row(20.0, |mut row| {
@@ -884,6 +1515,18 @@ impl eframe::App for MyApp {
                                             row.col(|ui| {
                                                 ui.label(u64_to_string(fail.1));
                                             });
+                                            row.col(|ui| {
+                                                if ui
+                                                    .small_button("🖨")
+                                                    .on_hover_text("Print ticket")
+                                                    .clicked()
+                                                {
+                                                    print_ticket(&attempts_of(
+                                                        &self.log_master.read().unwrap(),
+                                                        &fail.0,
+                                                    ));
+                                                }
+                                            });
                                         });
                                     }
                                 });
@@ -926,6 +1569,7 @@ impl eframe::App for MyApp {
         }
 
         // Central panel
+        let combined = self.is_combined();
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.spacing_mut().scroll = egui::style::ScrollStyle::solid();
             ui.set_enabled(!self.loading);
@@ -952,6 +1596,30 @@ impl eframe::App for MyApp {
                     self.mode = AppMode::Plot;
                 }
 
+                if ui.button(MESSAGE_F[PARETO_LABEL][self.lang]).clicked() {
+                    self.mode = AppMode::Pareto;
+                }
+
+                if ui.button(MESSAGE_F[TREND_LABEL][self.lang]).clicked() {
+                    self.mode = AppMode::Trend;
+                }
+
+                if ui.button(MESSAGE_F[RETEST_LABEL][self.lang]).clicked() {
+                    self.mode = AppMode::Retest;
+                }
+
+                if ui.button(MESSAGE_F[WEAR_LABEL][self.lang]).clicked() {
+                    self.mode = AppMode::Wear;
+                }
+
+                if ui.button(MESSAGE_A[ALARMS_LABEL][self.lang]).clicked() {
+                    self.mode = AppMode::Alarms;
+                }
+
+                if ui.button(MESSAGE_RP[REPAIRS_LABEL][self.lang]).clicked() {
+                    self.mode = AppMode::Repairs;
+                }
+
                 // Right side first:
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                     if ui.button("Scan").clicked() {
@@ -961,13 +1629,27 @@ impl eframe::App for MyApp {
                     if ui.button("Daily Yield").clicked() {
                         self.daily_yield_vp.enable();
                     }
+
+                    if ui.button("Archive").clicked() {
+                        self.archive_vp.enable();
+                    }
+
+                    ui.toggle_value(&mut self.scanner.enabled, "Scanner")
+                        .on_hover_text("Scanned DMCs open the board report");
                 });
             });
 
             ui.separator();
 
+            // Plot and Export work with one product at a time
+            if combined && (self.mode == AppMode::Plot || self.mode == AppMode::Export) {
+                ui.label(
+                    RichText::new(MESSAGE[SELECT_PRODUCT][self.lang]).color(Color32::RED),
+                );
+            }
+
             // Plot mode
-            if self.mode == AppMode::Plot && !self.loading {
+            if self.mode == AppMode::Plot && !self.loading && !combined {
                 let lfh = self.log_master.read().unwrap();
                 let testlist = lfh.get_testlist();
                 let mut reset_plot = false;
@@ -991,6 +1673,12 @@ impl eframe::App for MyApp {
                                 .speed(1.0)
                                 .clamp_range(0..=20),
                         );
+
+                        ui.separator();
+                        ui.selectable_value(&mut self.plot_view, PlotView::TimeSeries, "Time series");
+                        ui.selectable_value(&mut self.plot_view, PlotView::Histogram, "Histogram");
+                        ui.selectable_value(&mut self.plot_view, PlotView::ByIndex, "By index");
+                        ui.selectable_value(&mut self.plot_view, PlotView::Spc, "SPC");
                     });
 
                     ui.separator();
@@ -1000,6 +1688,7 @@ impl eframe::App for MyApp {
                             self.selected_test = x;
                             println!("INFO: Loading results for test nbr {}!", self.selected_test);
                             self.selected_test_results = lfh.get_stats_for_test(self.selected_test);
+                            self.selected_test_stats = None;
                             reset_plot = true;
                             if self.selected_test_results.1.is_empty() {
                                 println!("\tERR: Loading failed!");
@@ -1009,120 +1698,131 @@ impl eframe::App for MyApp {
                         }
                     }
 
-                    // Insert plot here
-
-                    let ppoints: PlotPoints = self
-                        .selected_test_results
-                        .1
-                        .iter()
-                        .filter_map(|r| {
-                            if self.selected_test_index != 0 && self.selected_test_index != r.1 {
-                                return None;
-                            }
-
-                            if r.2 .0 == BResult::Unknown {
-                                return None;
-                            }
-                            Some([r.0 as f64, r.2 .1 as f64])
-                        })
-                        .collect();
-
-                    //Lim2 (f32,f32),     // UL - LL
-                    //Lim3 (f32,f32,f32)  // Nom - UL - LL
-                    let upper_limit_p: PlotPoints = self
-                        .selected_test_results
-                        .1
-                        .iter()
-                        .filter_map(|r| {
-                            if self.selected_test_index != 0 && self.selected_test_index != r.1 {
-                                return None;
-                            }
-
-                            if let TLimit::Lim3(_, x, _) = r.3 {
-                                Some([r.0 as f64, x as f64])
-                            } else if let TLimit::Lim2(x, _) = r.3 {
-                                Some([r.0 as f64, x as f64])
-                            } else {
-                                None
-                            }
-                        })
-                        .collect();
-
-                    let nominal_p: PlotPoints = self
-                        .selected_test_results
-                        .1
-                        .iter()
-                        .filter_map(|r| {
-                            if self.selected_test_index != 0 && self.selected_test_index != r.1 {
-                                return None;
-                            }
-
-                            if let TLimit::Lim3(x, _, _) = r.3 {
-                                Some([r.0 as f64, x as f64])
-                            } else {
-                                None
-                            }
-                        })
-                        .collect();
+                    // Only recalculated, if the results or the index changes
+                    if self.selected_test_stats.as_ref().map(|s| s.0) != Some(self.selected_test_index) {
+                        self.selected_test_stats = Some((
+                            self.selected_test_index,
+                            compute_statistics(&self.selected_test_results.1, self.selected_test_index),
+                        ));
+                    }
 
-                    let lower_limit_p: PlotPoints = self
-                        .selected_test_results
-                        .1
-                        .iter()
-                        .filter_map(|r| {
-                            if self.selected_test_index != 0 && self.selected_test_index != r.1 {
-                                return None;
+                    egui::SidePanel::right("test statistics")
+                        .resizable(false)
+                        .show_inside(ui, |ui| {
+                            if let Some((_, stats)) = &self.selected_test_stats {
+                                statistics_ui(ui, stats, self.lang);
                             }
+                        });
 
-                            if let TLimit::Lim3(_, _, x) = r.3 {
-                                Some([r.0 as f64, x as f64])
-                            } else if let TLimit::Lim2(_, x) = r.3 {
-                                Some([r.0 as f64, x as f64])
-                            } else {
-                                None
-                            }
-                        })
-                        .collect();
+                    match self.plot_view {
+                        PlotView::TimeSeries => {
+                            time_series_plot(
+                                ui,
+                                &self.selected_test_results.1,
+                                self.selected_test_index,
+                                &testlist[self.selected_test].0,
+                                self.selected_test_results.0.unit(),
+                                reset_plot,
+                            );
+                        }
+                        PlotView::Histogram => {
+                            let split = self.limitchanges.as_ref().is_some_and(|changes| {
+                                changes.iter().any(|c| c.0 == self.selected_test)
+                            });
+                            self.histogram_view.update(
+                                ui,
+                                &self.selected_test_results.1,
+                                self.selected_test_index,
+                                split,
+                                self.selected_test_results.0.unit(),
+                                reset_plot,
+                            );
+                        }
+                        PlotView::ByIndex => {
+                            index_compare_ui(
+                                ui,
+                                &self.selected_test_results.1,
+                                self.selected_test_results.0.unit(),
+                                reset_plot,
+                                self.lang,
+                            );
+                        }
+                        PlotView::Spc => {
+                            let values = get_selected_values(
+                                &self.selected_test_results.1,
+                                self.selected_test_index,
+                            );
+                            self.spc_view.update(
+                                ui,
+                                &values,
+                                self.selected_test_results.0.unit(),
+                                reset_plot,
+                            );
+                        }
+                    }
+                }
+            }
 
-                    let points = egui_plot::Points::new(ppoints)
-                        .highlight(true)
-                        .color(Color32::BLUE)
-                        .name(testlist[self.selected_test].0.to_owned());
+            // Pareto mode
+            if self.mode == AppMode::Pareto && !self.loading {
+                let names: Vec<String> = self.loaded_products.iter().map(|p| p.name.clone()).collect();
+                let (fl_change, clicked) = self.pareto_view.update(
+                    ui,
+                    &self.failures,
+                    if combined { &names } else { &[] },
+                    &mut self.fl_setting,
+                    self.lang,
+                );
 
-                    let upper_limit = Line::new(upper_limit_p).color(Color32::RED).name("MAX");
+                if fl_change {
+                    self.update_view();
+                } else if let Some(i) = clicked {
+                    let (product, name) = (self.failures[i].0, self.failures[i].1.name.clone());
+                    self.select_failure(product, name);
+                }
+            }
 
-                    let nominal = Line::new(nominal_p).color(Color32::GREEN).name("Nom");
+            // Trend mode
+            if self.mode == AppMode::Trend && !self.loading {
+                let names: Vec<String> = self.loaded_products.iter().map(|p| p.name.clone()).collect();
+                self.trend_view.update(
+                    ui,
+                    &self.failures,
+                    &self.hourly_stats,
+                    &self.shift_model,
+                    if combined { &names } else { &[] },
+                    self.lang,
+                );
+            }
 
-                    let lower_limit = Line::new(lower_limit_p).color(Color32::RED).name("MIN");
+            // Retest mode
+            if self.mode == AppMode::Retest && !self.loading {
+                let handlers = self.filtered_handlers();
+                if let Some(DMC) = self.retest_view.update(ui, &handlers, self.lang) {
+                    let lfh = handler_for_DMC(&self.loaded_products, &DMC)
+                        .unwrap_or(self.log_master.clone());
+                    self.info_vp.open(DMC, lfh);
+                }
+            }
 
-                    let mut plot = Plot::new("Test results")
-                        .custom_x_axes(vec![egui_plot::AxisHints::new_x().formatter(x_formatter)])
-                        .custom_y_axes(vec![egui_plot::AxisHints::new_y()
-                            .formatter(y_formatter)
-                            .label(self.selected_test_results.0.unit())])
-                        .coordinates_formatter(
-                            egui_plot::Corner::RightTop,
-                            egui_plot::CoordinatesFormatter::new(c_formater),
-                        )
-                        .label_formatter(|name, value| {
-                            if !name.is_empty() {
-                                format!("{}: {:+1.4E}", name, value.y)
-                            } else {
-                                "".to_owned()
-                            }
-                        })
-                        .height(ui.available_height() - 20.0);
+            // Fixture wear mode
+            if self.mode == AppMode::Wear && !self.loading {
+                let handlers = self.filtered_handlers();
+                self.wear_view
+                    .update(ui, &handlers, &self.shift_model, self.lang);
+            }
 
-                    if reset_plot {
-                        plot = plot.reset();
-                    }
+            // Alarm rules and history
+            if self.mode == AppMode::Alarms {
+                alarms_ui(ui, &self.alarms, self.lang);
+            }
 
-                    plot.show(ui, |plot_ui| {
-                        plot_ui.points(points);
-                        plot_ui.line(upper_limit);
-                        plot_ui.line(nominal);
-                        plot_ui.line(lower_limit);
-                    });
+            // Repair statistics
+            if self.mode == AppMode::Repairs {
+                if let Some(DMC) = repairs_ui(ui, &self.repairs, self.lang) {
+                    let lfh = handler_for_DMC(&self.loaded_products, &DMC)
+                        .unwrap_or(self.log_master.clone());
+                    self.info_vp.open(DMC, lfh);
                 }
             }
 
@@ -1178,7 +1878,11 @@ impl eframe::App for MyApp {
                                                     if draw_result_box(ui, r).clicked() {
                                                         self.info_vp.open_first_NOK(
                                                             DMC.clone(),
-                                                            self.log_master.clone(),
+                                                            handler_for_DMC(
+                                                                &self.loaded_products,
+                                                                DMC,
+                                                            )
+                                                            .unwrap_or(self.log_master.clone()),
                                                         )
                                                     }
                                                 }
@@ -1230,7 +1934,11 @@ impl eframe::App for MyApp {
                                                 {
                                                     self.info_vp.open_first_NOK(
                                                         mb.0.clone(),
-                                                        self.log_master.clone(),
+                                                        handler_for_DMC(
+                                                            &self.loaded_products,
+                                                            &mb.0,
+                                                        )
+                                                        .unwrap_or(self.log_master.clone()),
                                                     );
                                                 }
                                             }
@@ -1250,7 +1958,12 @@ impl eframe::App for MyApp {
                                                         self.info_vp.open_w_index(
                                                             mb.0.clone(),
                                                             sb_index,
-                                                            self.log_master.clone(),
+                                                            sb.start,
+                                                            handler_for_DMC(
+                                                                &self.loaded_products,
+                                                                &mb.0,
+                                                            )
+                                                            .unwrap_or(self.log_master.clone()),
                                                         );
                                                     }
                                                 }
@@ -1264,7 +1977,7 @@ impl eframe::App for MyApp {
             }
 
             // Export mode
-            if self.mode == AppMode::Export {
+            if self.mode == AppMode::Export && !combined {
                 ui.heading(MESSAGE_E[SETTINGS][self.lang]);
                 ui.checkbox(
                     &mut self.export_settings.vertical,
@@ -1305,16 +2018,43 @@ impl eframe::App for MyApp {
 
                 ui.separator();
 
+                ui.horizontal(|ui| {
+                    ui.monospace(MESSAGE_E[EXPORT_FORMAT][self.lang]);
+                    ui.selectable_value(&mut self.export_format, ExportFormat::Xlsx, "XLSX");
+                    ui.selectable_value(&mut self.export_format, ExportFormat::Csv, "CSV");
+                    ui.selectable_value(&mut self.export_format, ExportFormat::JsonLines, "JSON Lines");
+                });
+
+                ui.separator();
+
                 if ui.button(MESSAGE_E[SAVE][self.lang]).clicked() && !self.loading {
+                    let extension = self.export_format.extension();
+                    if let Some(path) = rfd::FileDialog::new()
+                        .add_filter(extension.to_uppercase(), &[extension])
+                        .set_file_name(format!("out.{extension}"))
+                        .save_file()
+                    {
+                        let lfh = self.log_master.read().unwrap();
+                        if self.export_format == ExportFormat::Xlsx {
+                            lfh.export(path, &self.export_settings);
+                        } else {
+                            match export_long_to_file(&lfh, &path, &self.export_settings, self.export_format) {
+                                Ok(x) => println!("INFO: Exported {x} measurements to {}", path.display()),
+                                Err(e) => println!("ERR: Export failed: {e}"),
+                            }
+                        }
+                    }
+                }
+
+                if ui.button(MESSAGE_E[EXPORT_STATS][self.lang]).clicked() && !self.loading {
                     if let Some(path) = rfd::FileDialog::new()
                         .add_filter("XLSX", &["xlsx"])
-                        .set_file_name("out.xlsx")
+                        .set_file_name("statistics.xlsx")
                         .save_file()
                     {
-                        self.log_master
-                            .read()
-                            .unwrap()
-                            .export(path, &self.export_settings);
+                        if let Err(e) = export_statistics(path, &self.log_master.read().unwrap()) {
+                            println!("ERR: Failed to export statistics: {e}");
+                        }
                     }
                 }
 
@@ -1347,7 +2087,13 @@ impl eframe::App for MyApp {
         });
 
         if self.info_vp.enabled() {
-            self.info_vp.update(ctx, self.log_master.clone());
+            let handlers = self.filtered_handlers();
+            if let Some(DMC) = self
+                .info_vp
+                .update(ctx, &handlers, &mut self.repairs, &mut self.scanner)
+            {
+                self.open_scanned(DMC);
+            }
         }
 
         if self.scan_vp.enabled() {
@@ -1357,7 +2103,142 @@ impl eframe::App for MyApp {
         if self.daily_yield_vp.enabled() {
             self.daily_yield_vp.update(ctx);
         }
+
+        if self.archive_vp.enabled() {
+            self.archive_vp.update(ctx);
+        }
     }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.repairs.save_changes();
+    }
+}
+
+// Measured values of the selected test on the selected index (0 = all), ordered by time
+fn get_selected_values(results: &[(u64, usize, TResult, TLimit)], index: usize) -> Vec<(u64, f32)> {
+    let mut ret: Vec<(u64, f32)> = results
+        .iter()
+        .filter(|r| (index == 0 || index == r.1) && r.2 .0 != BResult::Unknown)
+        .map(|r| (r.0, r.2 .1))
+        .collect();
+
+    ret.sort_by_key(|k| k.0);
+    ret
+}
+
+// Results of the test over time, with the limits. 'index' 0 shows every board.
+fn time_series_plot(
+    ui: &mut egui::Ui,
+    results: &[(u64, usize, TResult, TLimit)],
+    index: usize,
+    name: &str,
+    unit: String,
+    reset_plot: bool,
+) {
+    let ppoints: PlotPoints = results
+        .iter()
+        .filter_map(|r| {
+            if index != 0 && index != r.1 {
+                return None;
+            }
+
+            if r.2 .0 == BResult::Unknown {
+                return None;
+            }
+            Some([r.0 as f64, r.2 .1 as f64])
+        })
+        .collect();
+
+    //Lim2 (f32,f32),     // UL - LL
+    //Lim3 (f32,f32,f32)  // Nom - UL - LL
+    let upper_limit_p: PlotPoints = results
+        .iter()
+        .filter_map(|r| {
+            if index != 0 && index != r.1 {
+                return None;
+            }
+
+            if let TLimit::Lim3(_, x, _) = r.3 {
+                Some([r.0 as f64, x as f64])
+            } else if let TLimit::Lim2(x, _) = r.3 {
+                Some([r.0 as f64, x as f64])
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    let nominal_p: PlotPoints = results
+        .iter()
+        .filter_map(|r| {
+            if index != 0 && index != r.1 {
+                return None;
+            }
+
+            if let TLimit::Lim3(x, _, _) = r.3 {
+                Some([r.0 as f64, x as f64])
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    let lower_limit_p: PlotPoints = results
+        .iter()
+        .filter_map(|r| {
+            if index != 0 && index != r.1 {
+                return None;
+            }
+
+            if let TLimit::Lim3(_, _, x) = r.3 {
+                Some([r.0 as f64, x as f64])
+            } else if let TLimit::Lim2(_, x) = r.3 {
+                Some([r.0 as f64, x as f64])
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    let points = egui_plot::Points::new(ppoints)
+        .highlight(true)
+        .color(Color32::BLUE)
+        .name(name.to_owned());
+
+    let upper_limit = Line::new(upper_limit_p).color(Color32::RED).name("MAX");
+
+    let nominal = Line::new(nominal_p).color(Color32::GREEN).name("Nom");
+
+    let lower_limit = Line::new(lower_limit_p).color(Color32::RED).name("MIN");
+
+    let mut plot = Plot::new("Test results")
+        .custom_x_axes(vec![egui_plot::AxisHints::new_x().formatter(x_formatter)])
+        .custom_y_axes(vec![egui_plot::AxisHints::new_y()
+            .formatter(y_formatter)
+            .label(unit)])
+        .coordinates_formatter(
+            egui_plot::Corner::RightTop,
+            egui_plot::CoordinatesFormatter::new(c_formater),
+        )
+        .label_formatter(|name, value| {
+            if !name.is_empty() {
+                format!("{}: {:+1.4E}", name, value.y)
+            } else {
+                "".to_owned()
+            }
+        })
+        .height(ui.available_height() - 20.0);
+
+    if reset_plot {
+        plot = plot.reset();
+    }
+
+    plot.show(ui, |plot_ui| {
+        plot_ui.points(points);
+        plot_ui.line(upper_limit);
+        plot_ui.line(nominal);
+        plot_ui.line(lower_limit);
+    });
 }
 
 // Formaters for the plot
@@ -0,0 +1,173 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+use crate::{FailureList, HourlyStats, LogFileHandler, MbStats, Yield};
+
+/*
+Several products can be loaded at the same time, each one has its own LogFileHandler.
+The views show either one product (selected with the product filter), or the combined total.
+*/
+
+// Log directory and its handler
+pub type ProductHandler = (PathBuf, Arc<RwLock<LogFileHandler>>);
+
+pub struct LoadedProduct {
+    pub name: String,
+    pub log_dir: PathBuf,
+    pub lfh: Arc<RwLock<LogFileHandler>>,
+}
+
+impl LoadedProduct {
+    pub fn new(name: &str, log_dir: PathBuf) -> Self {
+        LoadedProduct {
+            name: name.to_string(),
+            log_dir,
+            lfh: Arc::new(RwLock::new(LogFileHandler::new())),
+        }
+    }
+}
+
+// Handler of the product which has a log with this DMC (or multiboard DMC)
+pub fn handler_for_DMC(
+    products: &[LoadedProduct],
+    DMC: &str,
+) -> Option<Arc<RwLock<LogFileHandler>>> {
+    if products.len() == 1 {
+        return Some(products[0].lfh.clone());
+    }
+
+    products
+        .iter()
+        .find(|p| {
+            p.lfh
+                .read()
+                .unwrap()
+                .get_logs()
+                .iter()
+                .any(|l| l.get_DMC() == DMC || l.get_DMC_mb() == DMC)
+        })
+        .map(|p| p.lfh.clone())
+}
+
+pub fn sum_yields(yields: impl Iterator<Item = [Yield; 3]>) -> [Yield; 3] {
+    yields.fold([Yield(0, 0), Yield(0, 0), Yield(0, 0)], |acc, y| {
+        [
+            Yield(acc[0].0 + y[0].0, acc[0].1 + y[0].1),
+            Yield(acc[1].0 + y[1].0, acc[1].1 + y[1].1),
+            Yield(acc[2].0 + y[2].0, acc[2].1 + y[2].1),
+        ]
+    })
+}
+
+// Failures of every product in one list, ordered by the number of failures.
+pub fn combine_failures(failures: Vec<(usize, Vec<FailureList>)>) -> Vec<(usize, FailureList)> {
+    let mut ret: Vec<(usize, FailureList)> = failures
+        .into_iter()
+        .flat_map(|(product, list)| list.into_iter().map(move |f| (product, f)))
+        .collect();
+
+    ret.sort_by_key(|f| std::cmp::Reverse(f.1.total));
+    ret
+}
+
+// The hourly lists of the products merged by hour, in the same order as the LogFileHandler gives them.
+pub fn combine_hourly(mut hourly: Vec<Vec<HourlyStats>>) -> Vec<HourlyStats> {
+    if hourly.len() == 1 {
+        return hourly.pop().unwrap();
+    }
+
+    let descending = hourly
+        .iter()
+        .find(|h| h.len() > 1)
+        .is_some_and(|h| h[0].0 > h[h.len() - 1].0);
+
+    let mut merged: BTreeMap<u64, HourlyStats> = BTreeMap::new();
+    for hour in hourly.into_iter().flatten() {
+        if let Some(x) = merged.get_mut(&hour.0) {
+            x.1 += hour.1;
+            x.2 += hour.2;
+            x.3.extend(hour.3);
+        } else {
+            merged.insert(hour.0, hour);
+        }
+    }
+
+    let mut ret: Vec<HourlyStats> = merged.into_values().collect();
+    for hour in ret.iter_mut() {
+        hour.3.sort_by_key(|r| r.1);
+    }
+
+    if descending {
+        ret.reverse();
+    }
+    ret
+}
+
+// The multiboards of every product, ordered by their first test.
+pub fn combine_mb_results(mut results: Vec<Vec<MbStats>>) -> Vec<MbStats> {
+    if results.len() == 1 {
+        return results.pop().unwrap();
+    }
+
+    let first_test = |mb: &MbStats| mb.1.first().map_or(0, |r| r.start);
+    let descending = results
+        .iter()
+        .find(|r| r.len() > 1)
+        .is_some_and(|r| first_test(&r[0]) > first_test(&r[r.len() - 1]));
+
+    let mut ret: Vec<MbStats> = results.into_iter().flatten().collect();
+    ret.sort_by_key(first_test);
+
+    if descending {
+        ret.reverse();
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BResult;
+
+    #[test]
+    fn yields_are_summed() {
+        let a = [Yield(10, 1), Yield(9, 2), Yield(11, 0)];
+        let b = [Yield(5, 5), Yield(4, 6), Yield(8, 2)];
+
+        let sum = sum_yields([a, b].into_iter());
+        assert_eq!((sum[0].0, sum[0].1), (15, 6));
+        assert_eq!((sum[1].0, sum[1].1), (13, 8));
+        assert_eq!((sum[2].0, sum[2].1), (19, 2));
+    }
+
+    #[test]
+    fn hours_are_merged_in_the_original_order() {
+        let a: Vec<HourlyStats> = vec![
+            (
+                24010210,
+                3,
+                1,
+                vec![(BResult::Pass, 1500, "A2".to_string())],
+            ),
+            (24010209, 2, 0, vec![]),
+        ];
+        let b: Vec<HourlyStats> = vec![
+            (24010211, 1, 0, vec![]),
+            (
+                24010210,
+                4,
+                2,
+                vec![(BResult::Fail, 1000, "B1".to_string())],
+            ),
+        ];
+
+        let merged = combine_hourly(vec![a, b]);
+        let hours: Vec<u64> = merged.iter().map(|h| h.0).collect();
+        assert_eq!(hours, [24010211, 24010210, 24010209]);
+
+        assert_eq!((merged[1].1, merged[1].2), (7, 3));
+        let boards: Vec<&str> = merged[1].3.iter().map(|r| r.2.as_str()).collect();
+        assert_eq!(boards, ["B1", "A2"]);
+    }
+}
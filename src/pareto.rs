@@ -0,0 +1,155 @@
+use egui::Color32;
+use egui_plot::{Bar, BarChart, Line, Plot, PlotPoints, Points};
+
+use crate::{FailureList, FlSettings};
+
+pub struct ParetoView {
+    top: usize,
+}
+
+impl ParetoView {
+    pub fn default() -> Self {
+        ParetoView { top: 20 }
+    }
+
+    /*
+    Pareto chart of the failures: the 'top' most frequent failures as bars, and the cumulative
+    percentage of all the failures as a line, on the right axis.
+    'failures' has to be ordered by the number of failures, 'names' are the product names,
+    only used when not empty (combined view).
+    Returns true if the FlSettings changed, and the index of the clicked bar.
+    */
+    pub fn update(
+        &mut self,
+        ui: &mut egui::Ui,
+        failures: &[(usize, FailureList)],
+        names: &[String],
+        fl_setting: &mut FlSettings,
+        lang: usize,
+    ) -> (bool, Option<usize>) {
+        let mut fl_change = false;
+
+        ui.horizontal(|ui| {
+            fl_change = ui
+                .selectable_value(
+                    fl_setting,
+                    FlSettings::FirstPass,
+                    crate::MESSAGE[crate::FIRST_T][lang],
+                )
+                .changed()
+                || ui
+                    .selectable_value(
+                        fl_setting,
+                        FlSettings::All,
+                        crate::MESSAGE[crate::TOTAL][lang],
+                    )
+                    .changed()
+                || ui
+                    .selectable_value(
+                        fl_setting,
+                        FlSettings::AfterRetest,
+                        crate::MESSAGE[crate::AFTER_RT][lang],
+                    )
+                    .changed();
+
+            ui.separator();
+            ui.label(crate::MESSAGE_F[crate::TOP][lang]);
+            ui.add(
+                egui::DragValue::new(&mut self.top)
+                    .speed(1.0)
+                    .clamp_range(5..=100),
+            );
+        });
+
+        let total: usize = failures.iter().map(|f| f.1.total).sum();
+        if total == 0 {
+            return (fl_change, None);
+        }
+
+        let shown = &failures[..failures.len().min(self.top)];
+        let labels: Vec<String> = shown
+            .iter()
+            .map(|(product, f)| match names.get(*product) {
+                Some(p) if names.len() > 1 => format!("{} ({})", f.name, p),
+                _ => f.name.clone(),
+            })
+            .collect();
+
+        // The cumulative percentage is scaled to the largest bar, the right axis shows it in %
+        let scale = shown[0].1.total as f64;
+        let mut cumulative: usize = 0;
+        let mut bars: Vec<Bar> = Vec::new();
+        let mut line: Vec<[f64; 2]> = Vec::new();
+        for (i, (_, f)) in shown.iter().enumerate() {
+            cumulative += f.total;
+            bars.push(
+                Bar::new(i as f64 + 1.0, f.total as f64)
+                    .name(&labels[i])
+                    .width(0.7)
+                    .fill(Color32::LIGHT_RED),
+            );
+            line.push([i as f64 + 1.0, cumulative as f64 / total as f64 * scale]);
+        }
+
+        let x_labels = labels.clone();
+        let mut clicked = None;
+
+        Plot::new("Pareto")
+            .custom_x_axes(vec![egui_plot::AxisHints::new_x().formatter(
+                move |mark, _, _| {
+                    let i = mark.value.round();
+                    if (mark.value - i).abs() > 0.01 || i < 1.0 {
+                        return String::new();
+                    }
+                    x_labels
+                        .get(i as usize - 1)
+                        .map_or(String::new(), |l| l.chars().take(12).collect())
+                },
+            )])
+            .custom_y_axes(vec![
+                egui_plot::AxisHints::new_y().label("n"),
+                egui_plot::AxisHints::new_y()
+                    .label(crate::MESSAGE_F[crate::CUMULATIVE][lang])
+                    .placement(egui_plot::HPlacement::Right)
+                    .formatter(move |mark, _, _| format!("{:.0}%", mark.value / scale * 100.0)),
+            ])
+            .label_formatter(move |name, value| {
+                if name == crate::MESSAGE_F[crate::CUMULATIVE][lang] {
+                    format!("{}: {:.1}%", name, value.y / scale * 100.0)
+                } else if !name.is_empty() {
+                    format!("{}: {}", name, value.y)
+                } else {
+                    String::new()
+                }
+            })
+            .allow_drag(false)
+            .allow_scroll(false)
+            .allow_zoom(false)
+            .allow_boxed_zoom(false)
+            .height(ui.available_height() - 20.0)
+            .show(ui, |plot_ui| {
+                plot_ui.bar_chart(BarChart::new(bars).color(Color32::RED));
+                plot_ui.line(
+                    Line::new(PlotPoints::from(line.clone()))
+                        .color(Color32::BLUE)
+                        .name(crate::MESSAGE_F[crate::CUMULATIVE][lang]),
+                );
+                plot_ui.points(
+                    Points::new(PlotPoints::from(line))
+                        .color(Color32::BLUE)
+                        .radius(3.0),
+                );
+
+                if plot_ui.response().clicked() {
+                    if let Some(pointer) = plot_ui.pointer_coordinate() {
+                        let i = pointer.x.round();
+                        if i >= 1.0 && (i as usize) <= shown.len() && pointer.y >= 0.0 {
+                            clicked = Some(i as usize - 1);
+                        }
+                    }
+                }
+            });
+
+        (fl_change, clicked)
+    }
+}
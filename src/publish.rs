@@ -0,0 +1,469 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::Duration;
+
+use chrono::Local;
+use serde_json::{json, Value};
+
+use crate::{u64_to_datetime, FlSettings, LoadedProduct, Yield};
+
+/*
+Publishes the live status as JSON after each statistics update, for the line dashboards.
+Configured in a text file, with one target per line:
+
+    # POST the status to a dashboard (only plain http is supported)
+    post http://10.1.2.3:8080/ict/status
+    # serve the latest status at http://<this pc>:8088/
+    serve 8088
+
+The status contains the yields and hourly statistics of every loaded product,
+and the failures since the previous update.
+*/
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone)]
+struct PostTarget {
+    host: String,
+    port: u16,
+    path: String,
+}
+
+impl PostTarget {
+    // Only "http://host[:port][/path]" URLs are accepted
+    fn parse(url: &str) -> Option<Self> {
+        let rest = url.strip_prefix("http://")?;
+        let (host, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, "/"),
+        };
+        let (host, port) = match host.rsplit_once(':') {
+            Some((h, p)) => (h, p.parse().ok()?),
+            None => (host, 80),
+        };
+        if host.is_empty() {
+            return None;
+        }
+
+        Some(PostTarget {
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+        })
+    }
+
+    fn post(&self, body: &str) -> std::io::Result<()> {
+        let addr = (self.host.as_str(), self.port)
+            .to_socket_addrs()?
+            .next()
+            .ok_or(std::io::ErrorKind::NotFound)?;
+        let mut stream = TcpStream::connect_timeout(&addr, TIMEOUT)?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+
+        write!(
+            stream,
+            "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.path,
+            self.host,
+            body.len(),
+            body
+        )?;
+
+        let mut status = String::new();
+        BufReader::new(stream).read_line(&mut status)?;
+        match status.split_whitespace().nth(1) {
+            Some(code) if code.starts_with('2') => Ok(()),
+            _ => Err(std::io::Error::other(format!(
+                "unexpected response: {}",
+                status.trim()
+            ))),
+        }
+    }
+}
+
+// Answers every request with the latest status
+fn serve(listener: TcpListener, status: Arc<RwLock<String>>) {
+    for stream in listener.incoming() {
+        let Ok(mut stream) = stream else {
+            continue;
+        };
+        let _ = stream.set_read_timeout(Some(TIMEOUT));
+
+        // The request itself doesn't matter, but it has to be read before answering
+        let mut buf = [0u8; 4096];
+        let _ = stream.read(&mut buf);
+
+        let body = status.read().unwrap().clone();
+        let _ = write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+    }
+}
+
+fn yields_to_json(yields: &[Yield; 3]) -> Value {
+    let y = |y: &Yield| json!({ "pass": y.0, "fail": y.1, "percentage": y.precentage() });
+    json!({
+        "first_pass": y(&yields[0]),
+        "after_retest": y(&yields[1]),
+        "total": y(&yields[2]),
+    })
+}
+
+fn time_to_string(t: u64) -> String {
+    u64_to_datetime(t).map_or(t.to_string(), |t| t.format("%F %T").to_string())
+}
+
+// Starts answering the requests on 'listener' in the background, returns the status to update
+fn start_server(listener: TcpListener) -> Arc<RwLock<String>> {
+    let status = Arc::new(RwLock::new(String::from("{}")));
+    let status_c = status.clone();
+    thread::spawn(move || serve(listener, status_c));
+    status
+}
+
+// (product, test, DMC, time)
+type FailureKey = (String, String, String, u64);
+
+pub struct Publisher {
+    targets: Vec<PostTarget>,
+    status: Option<Arc<RwLock<String>>>, // latest status, if the local server is running
+    published: Option<HashSet<FailureKey>>, // failures already published, None before the first update
+}
+
+impl Publisher {
+    // Loads the targets from 'path', and starts the local server if needed.
+    // Without a config file nothing is published.
+    pub fn load(path: &str) -> Self {
+        let mut ret = Publisher {
+            targets: Vec::new(),
+            status: None,
+            published: None,
+        };
+
+        let Ok(content) = fs::read_to_string(path) else {
+            return ret;
+        };
+
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(url) = line.strip_prefix("post ") {
+                match PostTarget::parse(url.trim()) {
+                    Some(target) => ret.targets.push(target),
+                    None => println!("ERR: Invalid publish URL: {url}"),
+                }
+            } else if let Some(port) = line.strip_prefix("serve ") {
+                let Ok(port) = port.trim().parse::<u16>() else {
+                    println!("ERR: Invalid publish port: {port}");
+                    continue;
+                };
+                if ret.status.is_some() {
+                    println!("ERR: Only one local endpoint is supported, ignoring port {port}");
+                    continue;
+                }
+
+                match TcpListener::bind(("0.0.0.0", port)) {
+                    Ok(listener) => {
+                        println!("INFO: Serving the live status on port {port}");
+                        ret.status = Some(start_server(listener));
+                    }
+                    Err(e) => println!("ERR: Failed to listen on port {port}: {e}"),
+                }
+            } else {
+                println!("ERR: Unknown publish setting: {line}");
+            }
+        }
+
+        ret
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.targets.is_empty() || self.status.is_some()
+    }
+
+    // Called when new logs are loaded manually, so the old failures are not sent again as new
+    pub fn clear(&mut self) {
+        self.published = None;
+    }
+
+    // Failures not published yet, 'failures' are all the failures of the loaded logs.
+    // On the first update they are only noted, nothing is new.
+    // Each loaded failure is remembered, as the logs of the products can arrive in any order.
+    // The ones no longer loaded are forgotten, so the set doesn't grow during a long session.
+    fn take_new_failures(&mut self, failures: Vec<FailureKey>) -> Vec<FailureKey> {
+        let current: HashSet<FailureKey> = failures.into_iter().collect();
+
+        let mut ret: Vec<FailureKey> = match &self.published {
+            Some(published) => current.difference(published).cloned().collect(),
+            None => Vec::new(),
+        };
+        ret.sort_by_key(|f| f.3);
+
+        self.published = Some(current);
+        ret
+    }
+
+    // Builds the status of the products, and sends it to the targets in the background.
+    pub fn publish(&mut self, products: &[LoadedProduct]) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let mut status_products = Vec::new();
+        let mut failures = Vec::new();
+
+        for product in products {
+            let lfh = product.lfh.read().unwrap();
+
+            let hourly: Vec<Value> = lfh
+                .get_hourly_mb_stats()
+                .iter()
+                .map(|h| json!({ "hour": time_to_string(h.0 * 10000), "pass": h.1, "fail": h.2 }))
+                .collect();
+
+            status_products.push(json!({
+                "name": product.name,
+                "yields": yields_to_json(&lfh.get_yields()),
+                "mb_yields": yields_to_json(&lfh.get_mb_yields()),
+                "hourly": hourly,
+            }));
+
+            for failure in lfh.get_failures(FlSettings::All) {
+                for (DMC, time) in failure.failed {
+                    failures.push((product.name.clone(), failure.name.clone(), DMC, time));
+                }
+            }
+        }
+
+        let new_failures: Vec<Value> = self
+            .take_new_failures(failures)
+            .into_iter()
+            .map(|(product, test, DMC, time)| {
+                json!({
+                    "product": product,
+                    "test": test,
+                    "DMC": DMC,
+                    "time": time_to_string(time),
+                })
+            })
+            .collect();
+
+        let body = json!({
+            "time": Local::now().format("%F %T").to_string(),
+            "products": status_products,
+            "new_failures": new_failures,
+        })
+        .to_string();
+
+        if let Some(status) = &self.status {
+            *status.write().unwrap() = body.clone();
+        }
+
+        if !self.targets.is_empty() {
+            let targets = self.targets.clone();
+            thread::spawn(move || {
+                for target in targets {
+                    if let Err(e) = target.post(&body) {
+                        println!(
+                            "ERR: Failed to post the status to {}:{}{}: {e}",
+                            target.host, target.port, target.path
+                        );
+                    }
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn publisher(targets: Vec<PostTarget>, status: Option<Arc<RwLock<String>>>) -> Publisher {
+        Publisher {
+            targets,
+            status,
+            published: None,
+        }
+    }
+
+    fn failure(product: &str, DMC: &str, time: u64) -> FailureKey {
+        (
+            product.to_string(),
+            "R101".to_string(),
+            DMC.to_string(),
+            time,
+        )
+    }
+
+    #[test]
+    fn parse_post_target() {
+        let t = PostTarget::parse("http://10.1.2.3:8080/ict/status").unwrap();
+        assert_eq!(
+            (t.host.as_str(), t.port, t.path.as_str()),
+            ("10.1.2.3", 8080, "/ict/status")
+        );
+
+        let t = PostTarget::parse("http://dashboard").unwrap();
+        assert_eq!(
+            (t.host.as_str(), t.port, t.path.as_str()),
+            ("dashboard", 80, "/")
+        );
+
+        assert!(PostTarget::parse("https://dashboard/status").is_none());
+        assert!(PostTarget::parse("http://dashboard:port/status").is_none());
+        assert!(PostTarget::parse("http://:8080/status").is_none());
+    }
+
+    #[test]
+    fn new_failures_are_published_once() {
+        let mut p = publisher(Vec::new(), None);
+
+        // First update: only noted
+        assert!(p
+            .take_new_failures(vec![failure("A", "1", 240101120000)])
+            .is_empty());
+
+        // Same second as the last one, and an older failure of a product whose logs arrived later
+        let new = p.take_new_failures(vec![
+            failure("A", "1", 240101120000),
+            failure("A", "2", 240101120000),
+            failure("B", "3", 240101110000),
+        ]);
+        assert_eq!(
+            new,
+            vec![
+                failure("B", "3", 240101110000),
+                failure("A", "2", 240101120000)
+            ]
+        );
+
+        assert!(p
+            .take_new_failures(vec![
+                failure("A", "1", 240101120000),
+                failure("A", "2", 240101120000),
+                failure("B", "3", 240101110000),
+            ])
+            .is_empty());
+
+        p.clear();
+        assert!(p
+            .take_new_failures(vec![failure("C", "4", 240101130000)])
+            .is_empty());
+    }
+
+    #[test]
+    fn failures_no_longer_loaded_are_forgotten() {
+        let mut p = publisher(Vec::new(), None);
+
+        p.take_new_failures(vec![
+            failure("A", "1", 240101120000),
+            failure("A", "2", 240101130000),
+        ]);
+        p.take_new_failures(vec![failure("A", "2", 240101130000)]);
+
+        assert_eq!(
+            p.published,
+            Some(HashSet::from([failure("A", "2", 240101130000)]))
+        );
+    }
+
+    // Accepts one request, answers with 'response', returns the request line and the body
+    fn mock_server(
+        listener: TcpListener,
+        response: &'static str,
+    ) -> thread::JoinHandle<(String, String)> {
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some(x) = line.to_lowercase().strip_prefix("content-length:") {
+                    length = x.trim().parse().unwrap();
+                }
+            }
+
+            let mut body = vec![0u8; length];
+            reader.read_exact(&mut body).unwrap();
+            reader.get_mut().write_all(response.as_bytes()).unwrap();
+
+            (
+                request_line.trim().to_string(),
+                String::from_utf8(body).unwrap(),
+            )
+        })
+    }
+
+    fn check_status(body: &str) {
+        let status: Value = serde_json::from_str(body).unwrap();
+        assert!(status["time"].is_string());
+        assert_eq!(status["products"], json!([]));
+        assert_eq!(status["new_failures"], json!([]));
+    }
+
+    #[test]
+    fn post_to_local_listener() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = mock_server(listener, "HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n");
+
+        let target = PostTarget::parse(&format!("http://127.0.0.1:{port}/ict/status")).unwrap();
+        publisher(vec![target], None).publish(&[]);
+
+        let (request_line, body) = server.join().unwrap();
+        assert_eq!(request_line, "POST /ict/status HTTP/1.1");
+        check_status(&body);
+    }
+
+    #[test]
+    fn post_error_response() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = mock_server(listener, "HTTP/1.1 500 Internal Server Error\r\n\r\n");
+
+        let target = PostTarget::parse(&format!("http://127.0.0.1:{port}/")).unwrap();
+        assert!(target.post("{}").is_err());
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn serve_local_endpoint() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let status = start_server(listener);
+
+        publisher(Vec::new(), Some(status)).publish(&[]);
+
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        stream.set_read_timeout(Some(TIMEOUT)).unwrap();
+        write!(stream, "GET / HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+
+        assert!(head.starts_with("HTTP/1.1 200 OK"));
+        assert!(head.contains("Content-Type: application/json"));
+        check_status(body);
+    }
+}
@@ -0,0 +1,631 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+use chrono::Local;
+use egui::Color32;
+use egui_dropdown::DropDownBox;
+use egui_extras::{Column, TableBuilder};
+use rust_xlsxwriter::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{u64_to_datetime, Attempt, BResult, LogFileHandler};
+
+/*
+Repair log. The technician records the repair of a failed board in the board report window,
+the records are kept in a JSON file. Each repair is linked to the next attempt of the board
+after the failed one, so the statistics show which repairs fixed the board.
+*/
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Retest {
+    pub time: u64,         // YYMMDDHHMMSS
+    pub passed: bool,      // the board passed the retest
+    pub test_passed: bool, // the repaired test passed the retest
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Repair {
+    pub DMC: String,
+    pub test: String,
+    pub defect_code: String,
+    pub action: String,
+    pub comment: String,
+    pub recorded: String,    // "%F %T"
+    pub failed_attempt: u64, // start of the failed attempt, YYMMDDHHMMSS
+    pub retest: Option<Retest>,
+}
+
+impl Repair {
+    pub fn new(
+        DMC: &str,
+        test: &str,
+        defect_code: &str,
+        action: &str,
+        comment: &str,
+        failed_attempt: u64,
+    ) -> Self {
+        Repair {
+            DMC: DMC.to_string(),
+            test: test.to_string(),
+            defect_code: defect_code.trim().to_string(),
+            action: action.to_string(),
+            comment: comment.to_string(),
+            recorded: Local::now().format("%F %T").to_string(),
+            failed_attempt,
+            retest: None,
+        }
+    }
+}
+
+// (DMC, start of the test, result, failed tests) of a log
+type RetestLog = (String, u64, BResult, Vec<String>);
+
+pub struct RepairLog {
+    path: PathBuf,
+    pub repairs: Vec<Repair>,
+    changed: bool,                      // retests linked since the last save
+    log_counts: HashMap<String, usize>, // logs of the unlinked DMCs at the last check
+}
+
+impl RepairLog {
+    pub fn load(path: &str) -> Self {
+        let repairs = match fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                println!("ERR: Failed to read the repair log {path}: {e}");
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+
+        RepairLog {
+            path: PathBuf::from(path),
+            repairs,
+            changed: false,
+            log_counts: HashMap::new(),
+        }
+    }
+
+    fn save(&mut self) {
+        // Written to a temporary file first, so a crash can't lose the earlier records
+        let tmp_path = self.path.with_extension("tmp");
+        let result = serde_json::to_string_pretty(&self.repairs)
+            .map_err(|e| e.to_string())
+            .and_then(|s| fs::write(&tmp_path, s).map_err(|e| e.to_string()))
+            .and_then(|_| fs::rename(&tmp_path, &self.path).map_err(|e| e.to_string()));
+
+        match result {
+            Ok(_) => self.changed = false,
+            Err(e) => println!("ERR: Failed to save the repair log: {e}"),
+        }
+    }
+
+    // Writes the retests linked since the last save. They are only saved with the next repair
+    // or on exit, as the links can be found again from the logs anyway.
+    pub fn save_changes(&mut self) {
+        if self.changed {
+            self.save();
+        }
+    }
+
+    pub fn add(&mut self, repair: Repair) {
+        // Its board is checked again, even if it has no new logs
+        self.log_counts.remove(&repair.DMC);
+        self.repairs.push(repair);
+        self.save();
+    }
+
+    pub fn for_DMC<'a>(&'a self, DMC: &'a str) -> impl Iterator<Item = &'a Repair> {
+        self.repairs.iter().filter(move |r| r.DMC == DMC)
+    }
+
+    // Defect codes used so far, for the dropdown
+    pub fn defect_codes(&self) -> Vec<String> {
+        let mut codes: Vec<String> = self.repairs.iter().map(|r| r.defect_code.clone()).collect();
+        codes.sort();
+        codes.dedup();
+        codes
+    }
+
+    // Links the repairs which don't have a retest yet to the first attempt of the board after
+    // the failed one, from any of the handlers. Only the DMCs which got new logs are checked.
+    pub fn link_retests(&mut self, handlers: &[Arc<RwLock<LogFileHandler>>]) {
+        let unlinked: HashSet<&str> = self
+            .repairs
+            .iter()
+            .filter(|r| r.retest.is_none())
+            .map(|r| r.DMC.as_str())
+            .collect();
+        if unlinked.is_empty() {
+            return;
+        }
+
+        let mut logs: Vec<RetestLog> = Vec::new();
+        for handler in handlers {
+            let lfh = handler.read().unwrap();
+            for log in lfh.get_logs() {
+                if unlinked.contains(log.get_DMC()) {
+                    logs.push((
+                        log.get_DMC().to_string(),
+                        log.get_time_start(),
+                        log.get_result(),
+                        log.get_failed_tests(),
+                    ));
+                }
+            }
+        }
+
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for log in &logs {
+            *counts.entry(log.0.clone()).or_default() += 1;
+        }
+        logs.retain(|l| self.log_counts.get(&l.0) != counts.get(&l.0));
+        self.log_counts = counts;
+
+        for (i, retest) in find_retests(&self.repairs, &logs) {
+            self.repairs[i].retest = Some(retest);
+            self.changed = true;
+        }
+    }
+}
+
+// The first log of the board after the failed attempt, for every repair without a retest.
+// Returns the index of the repair and its retest.
+fn find_retests(repairs: &[Repair], logs: &[RetestLog]) -> Vec<(usize, Retest)> {
+    let mut by_DMC: HashMap<&str, Vec<&RetestLog>> = HashMap::new();
+    for log in logs {
+        by_DMC.entry(log.0.as_str()).or_default().push(log);
+    }
+
+    repairs
+        .iter()
+        .enumerate()
+        .filter(|(_, r)| r.retest.is_none())
+        .filter_map(|(i, repair)| {
+            let next = by_DMC
+                .get(repair.DMC.as_str())?
+                .iter()
+                .filter(|l| l.1 > repair.failed_attempt)
+                .min_by_key(|l| l.1)?;
+
+            Some((
+                i,
+                Retest {
+                    time: next.1,
+                    passed: next.2 == BResult::Pass,
+                    test_passed: !next.3.contains(&repair.test),
+                },
+            ))
+        })
+        .collect()
+}
+
+// Form of the board report window, for recording the repair of a failed attempt
+pub struct RepairForm {
+    test: String,
+    defect_code: String,
+    action: String,
+    comment: String,
+}
+
+impl RepairForm {
+    pub fn default() -> Self {
+        RepairForm {
+            test: String::new(),
+            defect_code: String::new(),
+            action: String::new(),
+            comment: String::new(),
+        }
+    }
+
+    // Shows the earlier repairs of the board too
+    pub fn ui(&mut self, ui: &mut egui::Ui, attempt: &Attempt, log: &mut RepairLog) {
+        egui::CollapsingHeader::new("🔧 Repair")
+            .id_source("repair form")
+            .show(ui, |ui| {
+                for r in log.for_DMC(&attempt.DMC) {
+                    ui.label(format!(
+                        "{} - {} - {} - {} - retest: {}",
+                        r.recorded,
+                        r.test,
+                        r.defect_code,
+                        r.action,
+                        retest_text(&r.retest)
+                    ));
+                }
+
+                if attempt.result != BResult::Fail {
+                    return;
+                }
+
+                let failed: Vec<&str> = attempt
+                    .tests
+                    .iter()
+                    .filter(|t| t.result == BResult::Fail)
+                    .map(|t| t.name.as_str())
+                    .collect();
+                if !failed.contains(&self.test.as_str()) {
+                    self.test = failed.first().map_or(String::new(), |t| t.to_string());
+                }
+                let codes = log.defect_codes();
+
+                egui::Grid::new("repair fields")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Test:");
+                        egui::ComboBox::from_id_source("repair test")
+                            .width(200.0)
+                            .selected_text(self.test.as_str())
+                            .show_ui(ui, |ui| {
+                                for t in &failed {
+                                    ui.selectable_value(&mut self.test, t.to_string(), *t);
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label("Defect code:");
+                        ui.add(DropDownBox::from_iter(
+                            codes.iter(),
+                            "defect codes",
+                            &mut self.defect_code,
+                            |ui, text| ui.selectable_label(false, text),
+                        ));
+                        ui.end_row();
+
+                        ui.label("Action:");
+                        ui.text_edit_singleline(&mut self.action);
+                        ui.end_row();
+
+                        ui.label("Comment:");
+                        ui.add(egui::TextEdit::multiline(&mut self.comment).desired_rows(2));
+                        ui.end_row();
+                    });
+
+                let valid = !self.test.is_empty() && !self.defect_code.trim().is_empty();
+                if ui.add_enabled(valid, egui::Button::new("Save")).clicked() {
+                    log.add(Repair::new(
+                        &attempt.DMC,
+                        &self.test,
+                        &self.defect_code,
+                        &self.action,
+                        &self.comment,
+                        attempt.time_start,
+                    ));
+                    self.action.clear();
+                    self.comment.clear();
+                }
+            });
+    }
+}
+
+// (key, repairs, retested, board passed the retest)
+type RepairGroup = (String, usize, usize, usize);
+
+fn group_by(repairs: &[Repair], key: impl Fn(&Repair) -> &str) -> Vec<RepairGroup> {
+    let mut groups: BTreeMap<&str, (usize, usize, usize)> = BTreeMap::new();
+    for repair in repairs {
+        let entry = groups.entry(key(repair)).or_default();
+        entry.0 += 1;
+        if let Some(retest) = repair.retest {
+            entry.1 += 1;
+            entry.2 += retest.passed as usize;
+        }
+    }
+
+    let mut ret: Vec<RepairGroup> = groups
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.0, v.1, v.2))
+        .collect();
+    ret.sort_by_key(|g| std::cmp::Reverse(g.1));
+    ret
+}
+
+fn fix_rate(retested: usize, passed: usize) -> Option<f64> {
+    (retested > 0).then(|| passed as f64 / retested as f64 * 100.0)
+}
+
+fn retest_text(retest: &Option<Retest>) -> String {
+    match retest {
+        Some(r) if r.passed => "PASS".to_string(),
+        Some(r) if r.test_passed => "FAIL (other test)".to_string(),
+        Some(_) => "FAIL".to_string(),
+        None => "-".to_string(),
+    }
+}
+
+fn time_text(t: u64) -> String {
+    u64_to_datetime(t).map_or(t.to_string(), |t| t.format("%F %T").to_string())
+}
+
+fn write_groups(
+    workbook: &mut Workbook,
+    name: &str,
+    groups: &[RepairGroup],
+    header_format: &Format,
+) -> Result<(), XlsxError> {
+    let worksheet = workbook.add_worksheet().set_name(name)?;
+    for (col, title) in [name, "Repairs", "Retested", "Passed retest", "Fixed (%)"]
+        .iter()
+        .enumerate()
+    {
+        worksheet.write_with_format(0, col as u16, *title, header_format)?;
+    }
+    worksheet.set_column_width_pixels(0, 200)?;
+
+    for (row, g) in groups.iter().enumerate() {
+        let row = row as u32 + 1;
+        worksheet.write(row, 0, &g.0)?;
+        worksheet.write(row, 1, g.1 as u32)?;
+        worksheet.write(row, 2, g.2 as u32)?;
+        worksheet.write(row, 3, g.3 as u32)?;
+        if let Some(x) = fix_rate(g.2, g.3) {
+            worksheet.write(row, 4, x)?;
+        }
+    }
+
+    Ok(())
+}
+
+// Every repair, and the statistics by defect code and by test
+pub fn export_repairs(path: &Path, log: &RepairLog) -> Result<(), XlsxError> {
+    let header_format = Format::new()
+        .set_bold()
+        .set_border_bottom(FormatBorder::Thin);
+    let mut workbook = Workbook::new();
+
+    let worksheet = workbook.add_worksheet().set_name("Repairs")?;
+    let header = [
+        "Recorded",
+        "DMC",
+        "Failed at",
+        "Test",
+        "Defect code",
+        "Action",
+        "Comment",
+        "Retest at",
+        "Retest result",
+        "Test passed",
+    ];
+    for (col, name) in header.iter().enumerate() {
+        worksheet.write_with_format(0, col as u16, *name, &header_format)?;
+    }
+    worksheet.set_freeze_panes(1, 0)?;
+
+    for (row, r) in log.repairs.iter().enumerate() {
+        let row = row as u32 + 1;
+        worksheet.write(row, 0, &r.recorded)?;
+        worksheet.write(row, 1, &r.DMC)?;
+        worksheet.write(row, 2, time_text(r.failed_attempt))?;
+        worksheet.write(row, 3, &r.test)?;
+        worksheet.write(row, 4, &r.defect_code)?;
+        worksheet.write(row, 5, &r.action)?;
+        worksheet.write(row, 6, &r.comment)?;
+        if let Some(retest) = r.retest {
+            worksheet.write(row, 7, time_text(retest.time))?;
+            worksheet.write(row, 8, retest_text(&r.retest))?;
+            worksheet.write(row, 9, retest.test_passed)?;
+        }
+    }
+    worksheet.autofilter(0, 0, log.repairs.len() as u32, header.len() as u16 - 1)?;
+
+    write_groups(
+        &mut workbook,
+        "Defect code",
+        &group_by(&log.repairs, |r| &r.defect_code),
+        &header_format,
+    )?;
+    write_groups(
+        &mut workbook,
+        "Test",
+        &group_by(&log.repairs, |r| &r.test),
+        &header_format,
+    )?;
+
+    workbook.save(path)?;
+    Ok(())
+}
+
+fn groups_ui(ui: &mut egui::Ui, id: &str, title: &str, groups: &[RepairGroup], lang: usize) {
+    ui.vertical(|ui| {
+        egui::Grid::new(id).striped(true).show(ui, |ui| {
+            ui.strong(title);
+            ui.strong(crate::MESSAGE_RP[crate::REPAIRS][lang]);
+            ui.strong(crate::MESSAGE_RP[crate::RETESTED_R][lang]);
+            ui.strong(crate::MESSAGE_RP[crate::FIXED][lang]);
+            ui.end_row();
+
+            for g in groups {
+                ui.label(&g.0);
+                ui.label(format!("{}", g.1));
+                ui.label(format!("{}", g.2));
+                ui.label(fix_rate(g.2, g.3).map_or("-".to_string(), |x| format!("{x:.1}%")));
+                ui.end_row();
+            }
+        });
+    });
+}
+
+// Repair statistics, returns the DMC of the clicked repair
+pub fn repairs_ui(ui: &mut egui::Ui, log: &RepairLog, lang: usize) -> Option<String> {
+    let text = |i: usize| crate::MESSAGE_RP[i][lang];
+    let mut clicked = None;
+
+    ui.horizontal(|ui| {
+        let retested = log.repairs.iter().filter(|r| r.retest.is_some()).count();
+        let passed = log
+            .repairs
+            .iter()
+            .filter(|r| r.retest.is_some_and(|r| r.passed))
+            .count();
+        ui.monospace(format!(
+            "{}: {} - {}: {} - {}: {}",
+            text(crate::REPAIRS),
+            log.repairs.len(),
+            text(crate::RETESTED_R),
+            retested,
+            text(crate::FIXED),
+            fix_rate(retested, passed).map_or("-".to_string(), |x| format!("{x:.1}%"))
+        ));
+
+        if ui.button(text(crate::EXPORT_REPAIRS)).clicked() {
+            if let Some(path) = rfd::FileDialog::new()
+                .add_filter("XLSX", &["xlsx"])
+                .set_file_name("repairs.xlsx")
+                .save_file()
+            {
+                if let Err(e) = export_repairs(&path, log) {
+                    println!("ERR: Failed to export the repairs: {e}");
+                }
+            }
+        }
+    });
+    ui.separator();
+
+    ui.horizontal_top(|ui| {
+        groups_ui(
+            ui,
+            "repairs by code",
+            text(crate::DEFECT_CODE),
+            &group_by(&log.repairs, |r| &r.defect_code),
+            lang,
+        );
+        ui.separator();
+        groups_ui(
+            ui,
+            "repairs by test",
+            crate::MESSAGE_R[crate::TEST][lang],
+            &group_by(&log.repairs, |r| &r.test),
+            lang,
+        );
+    });
+    ui.separator();
+
+    // Every repair, the newest first
+    let text_color = ui.visuals().text_color();
+    ui.push_id("repair list", |ui| {
+        TableBuilder::new(ui)
+            .striped(true)
+            .column(Column::initial(130.0))
+            .column(Column::initial(180.0).resizable(true))
+            .column(Column::initial(120.0).resizable(true))
+            .column(Column::initial(80.0).resizable(true))
+            .column(Column::initial(150.0).resizable(true))
+            .column(Column::initial(110.0))
+            .column(Column::remainder())
+            .header(20.0, |mut header| {
+                for name in [
+                    text(crate::RECORDED),
+                    "DMC",
+                    crate::MESSAGE_R[crate::TEST][lang],
+                    text(crate::DEFECT_CODE),
+                    text(crate::ACTION),
+                    text(crate::RETEST_RESULT),
+                    text(crate::COMMENT),
+                ] {
+                    header.col(|ui| {
+                        ui.strong(name);
+                    });
+                }
+            })
+            .body(|mut body| {
+                for repair in log.repairs.iter().rev() {
+                    let color = match repair.retest {
+                        Some(r) if r.passed => Color32::GREEN,
+                        Some(_) => Color32::RED,
+                        None => text_color,
+                    };
+
+                    body.row(16.0, |mut row| {
+                        row.col(|ui| {
+                            ui.label(&repair.recorded);
+                        });
+                        row.col(|ui| {
+                            if ui
+                                .add(egui::Label::new(&repair.DMC).sense(egui::Sense::click()))
+                                .clicked()
+                            {
+                                clicked = Some(repair.DMC.clone());
+                            }
+                        });
+                        row.col(|ui| {
+                            ui.label(&repair.test);
+                        });
+                        row.col(|ui| {
+                            ui.label(&repair.defect_code);
+                        });
+                        row.col(|ui| {
+                            ui.label(&repair.action);
+                        });
+                        row.col(|ui| {
+                            ui.colored_label(color, retest_text(&repair.retest));
+                        });
+                        row.col(|ui| {
+                            ui.label(&repair.comment);
+                        });
+                    });
+                }
+            });
+    });
+
+    clicked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repair(DMC: &str, test: &str, failed_attempt: u64) -> Repair {
+        Repair::new(DMC, test, "D1", "", "", failed_attempt)
+    }
+
+    fn log(DMC: &str, time: u64, result: BResult, failed: &[&str]) -> RetestLog {
+        (
+            DMC.to_string(),
+            time,
+            result,
+            failed.iter().map(|t| t.to_string()).collect(),
+        )
+    }
+
+    #[test]
+    fn repairs_are_linked_to_the_next_attempt() {
+        let repairs = vec![repair("B1", "R1", 10), repair("B2", "C1", 10)];
+        let logs = vec![
+            log("B1", 10, BResult::Fail, &["R1"]),
+            log("B1", 30, BResult::Pass, &[]),
+            log("B1", 20, BResult::Fail, &["C2"]),
+            log("B2", 5, BResult::Pass, &[]),
+        ];
+
+        let retests = find_retests(&repairs, &logs);
+        assert_eq!(retests.len(), 1);
+
+        let (i, retest) = retests[0];
+        assert_eq!(i, 0);
+        assert_eq!(retest.time, 20);
+        assert!(!retest.passed);
+        assert!(retest.test_passed);
+    }
+
+    #[test]
+    fn linked_repairs_are_not_changed() {
+        let mut linked = repair("B1", "R1", 10);
+        linked.retest = Some(Retest {
+            time: 30,
+            passed: true,
+            test_passed: true,
+        });
+        let repairs = vec![linked, repair("B1", "C1", 30)];
+        let logs = vec![
+            log("B1", 20, BResult::Fail, &["C1"]),
+            log("B1", 40, BResult::Fail, &["C1"]),
+        ];
+
+        let retests = find_retests(&repairs, &logs);
+        assert_eq!(retests.len(), 1);
+        assert_eq!(retests[0].0, 1);
+        assert_eq!(retests[0].1.time, 40);
+        assert!(!retests[0].1.test_passed);
+    }
+}
@@ -0,0 +1,404 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use egui::Color32;
+use egui_extras::{Column, TableBuilder};
+
+use crate::{u64_to_datetime, BResult, LogFile, LogFileHandler};
+
+/*
+Retest analysis. The logs are grouped by DMC, the attempts of a board are ordered by time.
+For each test failing on the first attempt it checks if the test passed on the second attempt,
+these are the likely false calls.
+*/
+
+pub struct TestRetest {
+    pub name: String,
+    pub first_fails: usize, // failed on the first attempt, and the board was retested
+    pub passed_on_retest: usize, // of those, passed on the second attempt
+    pub time_to_retest: f64, // average seconds between the first and second attempt
+}
+
+pub struct BoardAttempts {
+    pub DMC: String,
+    pub attempts: usize,
+    pub result: BResult, // of the last attempt
+    pub span: i64,       // seconds between the first and last attempt
+}
+
+pub struct RetestAnalysis {
+    pub boards: usize,
+    pub failed_first: usize,
+    pub retested: usize,
+    pub avg_retests: f64,
+    pub avg_retests_failed: f64,
+    pub avg_time_between: Option<f64>,
+    pub max_time_between: Option<i64>,
+    pub tests: Vec<TestRetest>,
+    pub retested_boards: Vec<BoardAttempts>, // ordered by the number of attempts
+}
+
+// The data of a log used by the analysis
+struct Attempt<'a> {
+    DMC: &'a str,
+    time_start: u64,
+    result: BResult,
+    failed_tests: Vec<String>,
+}
+
+// Seconds between the starts of two attempts
+fn seconds_between(a: &Attempt, b: &Attempt) -> Option<i64> {
+    let a = u64_to_datetime(a.time_start)?;
+    let b = u64_to_datetime(b.time_start)?;
+    Some((b - a).num_seconds())
+}
+
+pub fn analyze_retests(logs: &[&LogFile]) -> RetestAnalysis {
+    analyze(
+        logs.iter()
+            .map(|l| Attempt {
+                DMC: l.get_DMC(),
+                time_start: l.get_time_start(),
+                result: l.get_result(),
+                failed_tests: l.get_failed_tests(),
+            })
+            .collect(),
+    )
+}
+
+fn analyze(logs: Vec<Attempt>) -> RetestAnalysis {
+    let mut by_DMC: HashMap<&str, Vec<Attempt>> = HashMap::new();
+    for log in logs {
+        by_DMC.entry(log.DMC).or_default().push(log);
+    }
+
+    let mut tests: HashMap<String, (usize, usize, i64)> = HashMap::new();
+    let mut retested_boards: Vec<BoardAttempts> = Vec::new();
+    let mut failed_first = 0;
+    let mut retests_total = 0;
+    let mut retests_failed = 0;
+    let mut gaps: Vec<i64> = Vec::new();
+
+    for (DMC, attempts) in by_DMC.iter_mut() {
+        attempts.sort_by_key(|l| l.time_start);
+        let retests = attempts.len() - 1;
+        retests_total += retests;
+
+        if attempts[0].result == BResult::Fail {
+            failed_first += 1;
+            retests_failed += retests;
+        }
+
+        for pair in attempts.windows(2) {
+            if let Some(x) = seconds_between(&pair[0], &pair[1]) {
+                gaps.push(x);
+            }
+        }
+
+        if retests == 0 {
+            continue;
+        }
+
+        let gap = seconds_between(&attempts[0], &attempts[1]).unwrap_or(0);
+        let failed_again = &attempts[1].failed_tests;
+        for test in &attempts[0].failed_tests {
+            let passed = !failed_again.contains(test);
+            let entry = tests.entry(test.clone()).or_default();
+            entry.0 += 1;
+            entry.1 += passed as usize;
+            entry.2 += gap;
+        }
+
+        let last = attempts.last().unwrap();
+        retested_boards.push(BoardAttempts {
+            DMC: DMC.to_string(),
+            attempts: attempts.len(),
+            result: last.result,
+            span: seconds_between(&attempts[0], last).unwrap_or(0),
+        });
+    }
+
+    let mut tests: Vec<TestRetest> = tests
+        .into_iter()
+        .map(|(name, (first_fails, passed_on_retest, time))| TestRetest {
+            name,
+            first_fails,
+            passed_on_retest,
+            time_to_retest: time as f64 / first_fails as f64,
+        })
+        .collect();
+    tests.sort_by(|a, b| {
+        b.passed_on_retest
+            .cmp(&a.passed_on_retest)
+            .then(b.first_fails.cmp(&a.first_fails))
+    });
+
+    retested_boards.sort_by_key(|b| std::cmp::Reverse(b.attempts));
+
+    let boards = by_DMC.len();
+    RetestAnalysis {
+        boards,
+        failed_first,
+        retested: retested_boards.len(),
+        avg_retests: retests_total as f64 / boards.max(1) as f64,
+        avg_retests_failed: retests_failed as f64 / failed_first.max(1) as f64,
+        avg_time_between: (!gaps.is_empty())
+            .then(|| gaps.iter().sum::<i64>() as f64 / gaps.len() as f64),
+        max_time_between: gaps.iter().max().copied(),
+        tests,
+        retested_boards,
+    }
+}
+
+fn format_seconds(x: f64) -> String {
+    let x = x.round() as i64;
+    format!("{}:{:02}:{:02}", x / 3600, (x / 60) % 60, x % 60)
+}
+
+pub struct RetestView {
+    min_attempts: usize,
+    analysis: Option<RetestAnalysis>,
+}
+
+impl RetestView {
+    pub fn default() -> Self {
+        RetestView {
+            min_attempts: 2,
+            analysis: None,
+        }
+    }
+
+    // Has to be called when the logs change
+    pub fn reset(&mut self) {
+        self.analysis = None;
+    }
+
+    // Returns the DMC of the clicked board
+    pub fn update(
+        &mut self,
+        ui: &mut egui::Ui,
+        handlers: &[Arc<RwLock<LogFileHandler>>],
+        lang: usize,
+    ) -> Option<String> {
+        let analysis = self.analysis.get_or_insert_with(|| {
+            let locks: Vec<_> = handlers.iter().map(|h| h.read().unwrap()).collect();
+            let logs: Vec<&LogFile> = locks.iter().flat_map(|l| l.get_logs()).collect();
+            analyze_retests(&logs)
+        });
+
+        let text = |i: usize| crate::MESSAGE_R[i][lang];
+        let mut clicked = None;
+
+        egui::Grid::new("retest summary")
+            .striped(true)
+            .show(ui, |ui| {
+                ui.monospace(text(crate::BOARDS));
+                ui.monospace(format!("{}", analysis.boards));
+                ui.end_row();
+
+                ui.monospace(text(crate::FAILED_FIRST));
+                ui.monospace(format!("{}", analysis.failed_first));
+                ui.end_row();
+
+                ui.monospace(text(crate::RETESTED));
+                ui.monospace(format!("{}", analysis.retested));
+                ui.end_row();
+
+                ui.monospace(text(crate::AVG_RETESTS));
+                ui.monospace(format!(
+                    "{:.3} / {:.3}",
+                    analysis.avg_retests, analysis.avg_retests_failed
+                ));
+                ui.end_row();
+
+                ui.monospace(text(crate::TIME_BETWEEN));
+                ui.monospace(format!(
+                    "{} / {}",
+                    analysis
+                        .avg_time_between
+                        .map_or("-".to_string(), format_seconds),
+                    analysis
+                        .max_time_between
+                        .map_or("-".to_string(), |x| format_seconds(x as f64))
+                ));
+                ui.end_row();
+            });
+
+        ui.separator();
+
+        // Boards with too many attempts
+        egui::SidePanel::right("retest boards")
+            .resizable(true)
+            .show_inside(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(text(crate::MORE_THAN));
+                    ui.add(
+                        egui::DragValue::new(&mut self.min_attempts)
+                            .speed(1.0)
+                            .clamp_range(1..=20),
+                    );
+                });
+
+                ui.push_id("retest boards", |ui| {
+                    TableBuilder::new(ui)
+                        .striped(true)
+                        .column(Column::initial(200.0).resizable(true))
+                        .columns(Column::auto(), 2)
+                        .header(20.0, |mut header| {
+                            for name in ["DMC", text(crate::ATTEMPTS), text(crate::SPAN)] {
+                                header.col(|ui| {
+                                    ui.strong(name);
+                                });
+                            }
+                        })
+                        .body(|mut body| {
+                            for board in analysis
+                                .retested_boards
+                                .iter()
+                                .filter(|b| b.attempts > self.min_attempts)
+                            {
+                                body.row(16.0, |mut row| {
+                                    row.col(|ui| {
+                                        if ui
+                                            .add(
+                                                egui::Label::new(
+                                                    egui::RichText::new(&board.DMC)
+                                                        .color(board.result.into_dark_color()),
+                                                )
+                                                .sense(egui::Sense::click()),
+                                            )
+                                            .clicked()
+                                        {
+                                            clicked = Some(board.DMC.clone());
+                                        }
+                                    });
+                                    row.col(|ui| {
+                                        ui.label(format!("{}", board.attempts));
+                                    });
+                                    row.col(|ui| {
+                                        ui.label(format_seconds(board.span as f64));
+                                    });
+                                });
+                            }
+                        });
+                });
+            });
+
+        // Tests, the likely false calls first
+        let text_color = ui.visuals().text_color();
+        ui.push_id("retest tests", |ui| {
+            TableBuilder::new(ui)
+                .striped(true)
+                .column(Column::initial(200.0).resizable(true))
+                .columns(Column::initial(100.0), 4)
+                .column(Column::remainder())
+                .header(20.0, |mut header| {
+                    for name in [
+                        text(crate::TEST),
+                        text(crate::FAILED_FIRST),
+                        text(crate::PASSED_RETEST),
+                        text(crate::FAILED_AGAIN),
+                        text(crate::FALSE_CALL),
+                        text(crate::TIME_TO_RETEST),
+                    ] {
+                        header.col(|ui| {
+                            ui.strong(name);
+                        });
+                    }
+                })
+                .body(|mut body| {
+                    for test in &analysis.tests {
+                        let ratio = test.passed_on_retest as f64 / test.first_fails as f64;
+                        let color = if ratio >= 0.5 {
+                            Color32::RED
+                        } else {
+                            text_color
+                        };
+
+                        body.row(16.0, |mut row| {
+                            row.col(|ui| {
+                                ui.label(&test.name);
+                            });
+                            row.col(|ui| {
+                                ui.label(format!("{}", test.first_fails));
+                            });
+                            row.col(|ui| {
+                                ui.label(format!("{}", test.passed_on_retest));
+                            });
+                            row.col(|ui| {
+                                ui.label(format!("{}", test.first_fails - test.passed_on_retest));
+                            });
+                            row.col(|ui| {
+                                ui.colored_label(color, format!("{:.1}%", ratio * 100.0));
+                            });
+                            row.col(|ui| {
+                                ui.label(format_seconds(test.time_to_retest));
+                            });
+                        });
+                    }
+                });
+        });
+
+        clicked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attempt<'a>(DMC: &'a str, time_start: u64, failed: &[&str]) -> Attempt<'a> {
+        Attempt {
+            DMC,
+            time_start,
+            result: if failed.is_empty() {
+                BResult::Pass
+            } else {
+                BResult::Fail
+            },
+            failed_tests: failed.iter().map(|t| t.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn false_calls_pass_on_the_second_attempt() {
+        let analysis = analyze(vec![
+            attempt("B1", 240102100000, &["R1", "C2"]),
+            attempt("B1", 240102100100, &["C2"]),
+            attempt("B2", 240102100000, &["R1"]),
+            attempt("B2", 240102100300, &[]),
+            attempt("B3", 240102100000, &[]),
+        ]);
+
+        assert_eq!(analysis.boards, 3);
+        assert_eq!(analysis.failed_first, 2);
+        assert_eq!(analysis.retested, 2);
+
+        let r1 = analysis.tests.iter().find(|t| t.name == "R1").unwrap();
+        assert_eq!((r1.first_fails, r1.passed_on_retest), (2, 2));
+        assert_eq!(r1.time_to_retest, 120.0);
+
+        let c2 = analysis.tests.iter().find(|t| t.name == "C2").unwrap();
+        assert_eq!((c2.first_fails, c2.passed_on_retest), (1, 0));
+        assert_eq!(analysis.tests[0].name, "R1");
+    }
+
+    #[test]
+    fn attempts_are_ordered_by_time() {
+        let analysis = analyze(vec![
+            attempt("B1", 240102100500, &[]),
+            attempt("B1", 240102100000, &["R1"]),
+            attempt("B1", 240102100200, &["R1"]),
+        ]);
+
+        let board = &analysis.retested_boards[0];
+        assert_eq!(board.attempts, 3);
+        assert_eq!(board.result, BResult::Pass);
+        assert_eq!(board.span, 300);
+        assert_eq!(analysis.avg_retests_failed, 2.0);
+        assert_eq!(analysis.max_time_between, Some(180));
+
+        let r1 = &analysis.tests[0];
+        assert_eq!((r1.first_fails, r1.passed_on_retest), (1, 0));
+    }
+}
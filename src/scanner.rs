@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use egui::{Event, Key};
+
+/*
+Keyboard-wedge barcode scanners "type" the code, followed by Enter.
+A scanner types much faster than a person, so a burst of characters with short gaps,
+ended by Enter, is taken as a scanned code. Slower typing is ignored.
+egui gives no time for the events, only for the frame they arrive in, and a slow frame
+can easily take longer than the gaps of a scanner. So the gap is generous, and the code
+also has to be long enough and ended by Enter.
+While a burst is in progress, the characters are held back from the widgets, so a scan
+doesn't end up in the focused text field. If no Enter follows, they are given back.
+Every viewport has its own input, so the held characters are kept per viewport.
+*/
+
+// Longest gap between two frames with characters of a scan, in seconds
+const MAX_GAP: f64 = 0.3;
+// Shortest accepted code
+const MIN_LENGTH: usize = 6;
+
+pub struct BarcodeScanner {
+    pub enabled: bool,
+    held: HashMap<egui::ViewportId, (String, f64)>, // held back characters, time of the last one
+}
+
+impl BarcodeScanner {
+    pub fn default() -> Self {
+        BarcodeScanner {
+            enabled: false,
+            held: HashMap::new(),
+        }
+    }
+
+    // Has to be called every frame before the widgets, with the context of the viewport.
+    // Returns the scanned code, when a burst ends with Enter. The events of the scan are removed.
+    pub fn feed(&mut self, ctx: &egui::Context) -> Option<String> {
+        let (buffer, last) = self
+            .held
+            .entry(ctx.viewport_id())
+            .or_insert((String::new(), 0.0));
+
+        if !self.enabled {
+            if !buffer.is_empty() {
+                let text = std::mem::take(buffer);
+                ctx.input_mut(|i| i.events.insert(0, Event::Text(text)));
+            }
+            return None;
+        }
+
+        let ret = ctx.input_mut(|i| {
+            let (events, ret) = filter(buffer, last, i.time, std::mem::take(&mut i.events));
+            i.events = events;
+            ret
+        });
+
+        // Checked again after the gap, so the held characters of slow typing are given back
+        if !buffer.is_empty() {
+            ctx.request_repaint_after(Duration::from_secs_f64(MAX_GAP));
+        }
+
+        ret
+    }
+}
+
+// Takes the characters of a scan out of the events of one frame, arriving at 'time'.
+// 'buffer' and 'last' are the held characters and the time of the last one, kept between frames.
+// Returns the remaining events, and the scanned code if Enter ended a burst.
+fn filter(
+    buffer: &mut String,
+    last: &mut f64,
+    time: f64,
+    input: Vec<Event>,
+) -> (Vec<Event>, Option<String>) {
+    let mut ret = None;
+    let mut events = Vec::with_capacity(input.len() + 1);
+
+    // Too slow for a scanner, it was typed
+    if !buffer.is_empty() && time - *last > MAX_GAP {
+        events.push(Event::Text(std::mem::take(buffer)));
+    }
+
+    for event in input {
+        match event {
+            Event::Text(text) => {
+                buffer.push_str(&text);
+                *last = time;
+            }
+            Event::Key {
+                key: Key::Enter,
+                pressed: true,
+                ..
+            } if buffer.trim().len() >= MIN_LENGTH => {
+                ret = Some(buffer.trim().to_string());
+                buffer.clear();
+            }
+            // Any other key gets the held characters first, to keep the order
+            Event::Key { pressed: true, .. } if !buffer.is_empty() => {
+                events.push(Event::Text(std::mem::take(buffer)));
+                events.push(event);
+            }
+            _ => events.push(event),
+        }
+    }
+
+    (events, ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(s: &str) -> Event {
+        Event::Text(s.to_string())
+    }
+
+    fn enter() -> Event {
+        Event::Key {
+            key: Key::Enter,
+            physical_key: None,
+            pressed: true,
+            repeat: false,
+            modifiers: egui::Modifiers::NONE,
+        }
+    }
+
+    // Feeds the frames (time, events) one after the other, returns the events left and the codes
+    fn run(frames: Vec<(f64, Vec<Event>)>) -> (Vec<Event>, Vec<String>) {
+        let mut buffer = String::new();
+        let mut last = 0.0;
+        let mut events = Vec::new();
+        let mut codes = Vec::new();
+
+        for (time, input) in frames {
+            let (e, code) = filter(&mut buffer, &mut last, time, input);
+            events.extend(e);
+            codes.extend(code);
+        }
+
+        (events, codes)
+    }
+
+    #[test]
+    fn burst_over_slow_frames_is_a_scan() {
+        let (events, codes) = run(vec![
+            (1.0, vec![text("AB"), text("C")]),
+            (1.1, vec![text("12")]),
+            (1.25, vec![text("3"), enter()]),
+        ]);
+
+        assert_eq!(codes, ["ABC123"]);
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn slow_typing_is_given_back() {
+        let (events, codes) = run(vec![
+            (1.0, vec![text("A")]),
+            (1.5, vec![text("B")]),
+            (2.0, vec![]),
+        ]);
+
+        assert!(codes.is_empty());
+        assert!(matches!(&events[..], [Event::Text(a), Event::Text(b)] if a == "A" && b == "B"));
+    }
+
+    #[test]
+    fn short_code_keeps_the_enter() {
+        let (events, codes) = run(vec![(1.0, vec![text("ABC"), enter()])]);
+
+        assert!(codes.is_empty());
+        assert!(matches!(
+            &events[..],
+            [Event::Text(t), Event::Key { key: Key::Enter, .. }] if t == "ABC"
+        ));
+    }
+}
@@ -0,0 +1,307 @@
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Weekday};
+use std::fs;
+
+/*
+Shift definitions, loaded from a simple text file. One pattern per line:
+    <days>: <start>-<end> <start>-<end> ...
+<days> is "default", "weekday", "weekend" or a comma separated list of "mon" .. "sun".
+A more specific pattern overrides the less specific ones. Example:
+
+    # 3x8h on weekdays, 2x12h on weekends
+    default: 06:00-14:00 14:00-22:00 22:00-06:00
+    weekend: 06:00-18:00 18:00-06:00
+
+The production day starts with the first shift of the day. A shift with an end time
+before its start time continues on the next day.
+*/
+
+// Time periods for grouping the logs, shifts and days follow the shift model
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeBucket {
+    Hour,
+    Shift,
+    Day,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Shift {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl Shift {
+    // Start and end of the shift, if it is started on 'date'
+    fn on_date(&self, date: NaiveDate) -> (NaiveDateTime, NaiveDateTime) {
+        let start = date.and_time(self.start);
+        let mut end = date.and_time(self.end);
+        if end <= start {
+            end += Duration::try_days(1).unwrap();
+        }
+
+        (start, end)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DayPattern {
+    Default,
+    Weekday,
+    Weekend,
+    Day(Weekday),
+}
+
+impl DayPattern {
+    fn parse(s: &str) -> Option<Vec<Self>> {
+        let mut ret = Vec::new();
+        for day in s.split(',') {
+            ret.push(match day.trim().to_lowercase().as_str() {
+                "default" => DayPattern::Default,
+                "weekday" => DayPattern::Weekday,
+                "weekend" => DayPattern::Weekend,
+                x => DayPattern::Day(x.parse::<Weekday>().ok()?),
+            });
+        }
+
+        Some(ret)
+    }
+
+    // Higher is more specific, None if the pattern doesn't apply to the day.
+    fn matches(&self, day: Weekday) -> Option<u8> {
+        let weekend = matches!(day, Weekday::Sat | Weekday::Sun);
+        match self {
+            DayPattern::Default => Some(0),
+            DayPattern::Weekday if !weekend => Some(1),
+            DayPattern::Weekend if weekend => Some(1),
+            DayPattern::Day(x) if *x == day => Some(2),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ShiftModel {
+    patterns: Vec<(DayPattern, Vec<Shift>)>,
+}
+
+impl ShiftModel {
+    // The original hard-coded model: 3 shifts, starting at 06:00, 14:00 and 22:00
+    pub fn default() -> Self {
+        let t = |h| NaiveTime::from_hms_opt(h, 0, 0).unwrap();
+
+        ShiftModel {
+            patterns: vec![(
+                DayPattern::Default,
+                vec![
+                    Shift { start: t(6), end: t(14) },
+                    Shift { start: t(14), end: t(22) },
+                    Shift { start: t(22), end: t(6) },
+                ],
+            )],
+        }
+    }
+
+    // Loads the shift model from 'path', falls back to the default model on any error.
+    pub fn load(path: &str) -> Self {
+        let Ok(content) = fs::read_to_string(path) else {
+            println!("INFO: No shift config found at {path}, using the default shifts.");
+            return ShiftModel::default();
+        };
+
+        match ShiftModel::parse(&content) {
+            Ok(model) => model,
+            Err(line) => {
+                println!("ERR: Failed to parse shift config line: \"{line}\", using the default shifts.");
+                ShiftModel::default()
+            }
+        }
+    }
+
+    fn parse(content: &str) -> Result<Self, String> {
+        let parse_time = |s: &str| NaiveTime::parse_from_str(s.trim(), "%H:%M").ok();
+        let mut patterns = Vec::new();
+
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (days, shift_list) = line.split_once(':').ok_or(line.to_string())?;
+            let days = DayPattern::parse(days).ok_or(line.to_string())?;
+
+            let mut shifts = Vec::new();
+            for shift in shift_list.split_whitespace() {
+                let (start, end) = shift.split_once('-').ok_or(line.to_string())?;
+                shifts.push(Shift {
+                    start: parse_time(start).ok_or(line.to_string())?,
+                    end: parse_time(end).ok_or(line.to_string())?,
+                });
+            }
+
+            if shifts.is_empty() {
+                return Err(line.to_string());
+            }
+
+            shifts.sort_by_key(|s| s.start);
+            for day in days {
+                patterns.push((day, shifts.clone()));
+            }
+        }
+
+        if patterns.is_empty() {
+            return Err(String::new());
+        }
+
+        // Days without a matching pattern use the original model
+        if !patterns.iter().any(|p| p.0 == DayPattern::Default) {
+            patterns.append(&mut ShiftModel::default().patterns);
+        }
+
+        Ok(ShiftModel { patterns })
+    }
+
+    // Shifts of the production day starting on 'date', ordered by their start time.
+    pub fn shifts_for(&self, date: NaiveDate) -> &[Shift] {
+        self.patterns
+            .iter()
+            .filter_map(|(p, s)| p.matches(date.weekday()).map(|x| (x, s)))
+            .max_by_key(|(x, _)| *x)
+            .map(|(_, s)| s.as_slice())
+            .unwrap_or(&[])
+    }
+
+    // Start and end of the production day starting on 'date'.
+    // It lasts until the first shift of the next day starts.
+    pub fn production_day(&self, date: NaiveDate) -> (NaiveDateTime, NaiveDateTime) {
+        let first_start = |d: NaiveDate| {
+            d.and_time(
+                self.shifts_for(d)
+                    .first()
+                    .map_or(NaiveTime::MIN, |s| s.start),
+            )
+        };
+
+        (first_start(date), first_start(date.succ_opt().unwrap()))
+    }
+
+    // All shifts of the production day starting on 'date', as (start, end) pairs.
+    pub fn shifts_of_day(&self, date: NaiveDate) -> Vec<(NaiveDateTime, NaiveDateTime)> {
+        self.shifts_for(date)
+            .iter()
+            .map(|s| s.on_date(date))
+            .collect()
+    }
+
+    // Start and end of the shift active at 't', if there is one.
+    pub fn current_shift(&self, t: NaiveDateTime) -> Option<(NaiveDateTime, NaiveDateTime)> {
+        // A shift active at 't' could have been started on the previous day.
+        let yesterday = t.date().pred_opt().unwrap();
+        [yesterday, t.date()]
+            .into_iter()
+            .flat_map(|d| self.shifts_of_day(d))
+            .find(|(start, end)| *start <= t && t < *end)
+    }
+
+    // Start of the bucket containing 't'
+    pub fn bucket_start(&self, t: NaiveDateTime, bucket: TimeBucket) -> NaiveDateTime {
+        let hour = t.date().and_hms_opt(t.hour(), 0, 0).unwrap();
+
+        match bucket {
+            TimeBucket::Hour => hour,
+            TimeBucket::Shift => self.current_shift(hour).map_or(hour, |s| s.0),
+            TimeBucket::Day => {
+                let yesterday = t.date() - Duration::try_days(1).unwrap();
+                [t.date(), yesterday]
+                    .into_iter()
+                    .map(|d| self.production_day(d))
+                    .find(|(start, end)| *start <= hour && hour < *end)
+                    .map_or(t.date().and_hms_opt(0, 0, 0).unwrap(), |d| d.0)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dt(date: &str, time: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(&format!("{date} {time}"), "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    const CONFIG: &str = "
+        # 3x8h on weekdays, 2x12h on weekends
+        default: 06:00-14:00 14:00-22:00 22:00-06:00
+        weekend: 06:00-18:00 18:00-06:00
+    ";
+
+    #[test]
+    fn night_shift_crosses_midnight() {
+        let model = ShiftModel::parse(CONFIG).unwrap();
+
+        // 2024-03-05 is a Tuesday
+        let shifts = model.shifts_of_day(date("2024-03-05"));
+        assert_eq!(shifts.len(), 3);
+        assert_eq!(
+            shifts[2],
+            (dt("2024-03-05", "22:00"), dt("2024-03-06", "06:00"))
+        );
+
+        // After midnight it is still the shift started on the previous day
+        assert_eq!(
+            model.current_shift(dt("2024-03-06", "03:30")),
+            Some((dt("2024-03-05", "22:00"), dt("2024-03-06", "06:00")))
+        );
+    }
+
+    #[test]
+    fn production_day_ends_with_the_next_first_shift() {
+        let model = ShiftModel::parse(CONFIG).unwrap();
+
+        assert_eq!(
+            model.production_day(date("2024-03-05")),
+            (dt("2024-03-05", "06:00"), dt("2024-03-06", "06:00"))
+        );
+
+        // Saturday night shift runs into Sunday, which has the weekend pattern too
+        let shifts = model.shifts_of_day(date("2024-03-09"));
+        assert_eq!(
+            shifts,
+            vec![
+                (dt("2024-03-09", "06:00"), dt("2024-03-09", "18:00")),
+                (dt("2024-03-09", "18:00"), dt("2024-03-10", "06:00")),
+            ]
+        );
+    }
+
+    #[test]
+    fn more_specific_pattern_wins() {
+        let model =
+            ShiftModel::parse("weekday: 07:00-19:00 19:00-07:00\nfri: 07:00-15:00").unwrap();
+
+        assert_eq!(model.shifts_for(date("2024-03-05")).len(), 2);
+        assert_eq!(model.shifts_for(date("2024-03-08")).len(), 1);
+        // No weekend pattern, falls back to the default model
+        assert_eq!(model.shifts_for(date("2024-03-09")).len(), 3);
+    }
+
+    #[test]
+    fn malformed_lines_are_rejected() {
+        for line in [
+            "default 06:00-14:00",
+            "default: 06:00",
+            "default: 6-14",
+            "default: 25:00-06:00",
+            "someday: 06:00-14:00",
+            "default:",
+        ] {
+            assert_eq!(ShiftModel::parse(line).err(), Some(line.to_string()));
+        }
+
+        assert!(ShiftModel::parse("# only a comment").is_err());
+    }
+}
@@ -0,0 +1,418 @@
+use egui::Color32;
+use egui_plot::{HLine, Line, Plot, PlotPoints, Points};
+
+/*
+Statistical process control charts for the selected test.
+Individuals / moving range (I-MR) works on the single measurements,
+X-bar/R on consecutive subgroups of 'subgroup' measurements.
+The Nelson rules are evaluated on the I or X-bar chart.
+*/
+
+// Control chart constants for subgroup sizes 2..=10
+const D2: [f64; 9] = [
+    1.128, 1.693, 2.059, 2.326, 2.534, 2.704, 2.847, 2.970, 3.078,
+];
+const A2: [f64; 9] = [
+    1.880, 1.023, 0.729, 0.577, 0.483, 0.419, 0.373, 0.337, 0.308,
+];
+const D3: [f64; 9] = [0.0, 0.0, 0.0, 0.0, 0.0, 0.076, 0.136, 0.184, 0.223];
+const D4: [f64; 9] = [
+    3.267, 2.574, 2.282, 2.114, 2.004, 1.924, 1.864, 1.816, 1.777,
+];
+
+const RULES: [&str; 8] = [
+    "1 point beyond 3σ",
+    "9 points in a row on the same side",
+    "6 points in a row increasing or decreasing",
+    "14 points in a row alternating",
+    "2 of 3 points beyond 2σ on the same side",
+    "4 of 5 points beyond 1σ on the same side",
+    "15 points in a row within 1σ",
+    "8 points in a row beyond 1σ, on both sides",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpcChartType {
+    IndividualsMR,
+    XbarR,
+}
+
+pub struct ControlChart {
+    pub points: Vec<[f64; 2]>, // (time, value)
+    pub center: f64,
+    pub ucl: f64,
+    pub lcl: f64,
+}
+
+pub struct SpcResult {
+    pub main: ControlChart,  // I or X-bar
+    pub range: ControlChart, // MR or R
+    pub sigma: f64,
+    pub violations: Vec<(usize, usize)>, // (point, rule), rule is 1..=8
+}
+
+fn mean(x: &[f64]) -> f64 {
+    x.iter().sum::<f64>() / x.len() as f64
+}
+
+// 'values' has to be ordered by time.
+pub fn compute_spc(
+    values: &[(u64, f32)],
+    chart: SpcChartType,
+    subgroup: usize,
+) -> Option<SpcResult> {
+    let (main_points, range_points, sigma, range_factors) = match chart {
+        SpcChartType::IndividualsMR => {
+            if values.len() < 2 {
+                return None;
+            }
+
+            let main_points: Vec<[f64; 2]> =
+                values.iter().map(|v| [v.0 as f64, v.1 as f64]).collect();
+            let range_points: Vec<[f64; 2]> = main_points
+                .windows(2)
+                .map(|w| [w[1][0], (w[1][1] - w[0][1]).abs()])
+                .collect();
+
+            let mr_mean = mean(&range_points.iter().map(|p| p[1]).collect::<Vec<f64>>());
+            (main_points, range_points, mr_mean / D2[0], (D3[0], D4[0]))
+        }
+        SpcChartType::XbarR => {
+            let n = subgroup.clamp(2, 10);
+            if values.len() < n * 2 {
+                return None;
+            }
+
+            let mut main_points: Vec<[f64; 2]> = Vec::new();
+            let mut range_points: Vec<[f64; 2]> = Vec::new();
+            for group in values.chunks_exact(n) {
+                let x: Vec<f64> = group.iter().map(|v| v.1 as f64).collect();
+                let max = x.iter().cloned().fold(f64::MIN, f64::max);
+                let min = x.iter().cloned().fold(f64::MAX, f64::min);
+                let t = group.last().unwrap().0 as f64;
+
+                main_points.push([t, mean(&x)]);
+                range_points.push([t, max - min]);
+            }
+
+            let r_mean = mean(&range_points.iter().map(|p| p[1]).collect::<Vec<f64>>());
+            (
+                main_points,
+                range_points,
+                A2[n - 2] * r_mean / 3.0,
+                (D3[n - 2], D4[n - 2]),
+            )
+        }
+    };
+
+    let center = mean(&main_points.iter().map(|p| p[1]).collect::<Vec<f64>>());
+    let range_center = mean(&range_points.iter().map(|p| p[1]).collect::<Vec<f64>>());
+    let violations = nelson_rules(&main_points, center, sigma);
+
+    Some(SpcResult {
+        main: ControlChart {
+            points: main_points,
+            center,
+            ucl: center + 3.0 * sigma,
+            lcl: center - 3.0 * sigma,
+        },
+        range: ControlChart {
+            points: range_points,
+            center: range_center,
+            ucl: range_factors.1 * range_center,
+            lcl: range_factors.0 * range_center,
+        },
+        sigma,
+        violations,
+    })
+}
+
+// Returns (point, rule) pairs, the point is the one which completes the pattern.
+fn nelson_rules(points: &[[f64; 2]], center: f64, sigma: f64) -> Vec<(usize, usize)> {
+    let mut ret = Vec::new();
+    if sigma <= 0.0 {
+        return ret;
+    }
+
+    let y: Vec<f64> = points.iter().map(|p| p[1]).collect();
+    let z: Vec<f64> = y.iter().map(|v| (v - center) / sigma).collect();
+    let same_side = |w: &[f64], limit: f64, count: usize| {
+        w.iter().filter(|v| **v > limit).count() >= count
+            || w.iter().filter(|v| **v < -limit).count() >= count
+    };
+
+    for i in 0..z.len() {
+        if z[i].abs() > 3.0 {
+            ret.push((i, 1));
+        }
+
+        if i >= 8 && same_side(&z[i - 8..=i], 0.0, 9) {
+            ret.push((i, 2));
+        }
+
+        if i >= 5 {
+            let w = &y[i - 5..=i];
+            if w.windows(2).all(|p| p[1] > p[0]) || w.windows(2).all(|p| p[1] < p[0]) {
+                ret.push((i, 3));
+            }
+        }
+
+        if i >= 13 {
+            let diffs: Vec<f64> = y[i - 13..=i].windows(2).map(|p| p[1] - p[0]).collect();
+            if diffs.windows(2).all(|d| d[0] * d[1] < 0.0) {
+                ret.push((i, 4));
+            }
+        }
+
+        if i >= 2 && same_side(&z[i - 2..=i], 2.0, 2) {
+            ret.push((i, 5));
+        }
+
+        if i >= 4 && same_side(&z[i - 4..=i], 1.0, 4) {
+            ret.push((i, 6));
+        }
+
+        if i >= 14 && z[i - 14..=i].iter().all(|v| v.abs() < 1.0) {
+            ret.push((i, 7));
+        }
+
+        if i >= 7 {
+            let w = &z[i - 7..=i];
+            if w.iter().all(|v| v.abs() > 1.0)
+                && w.iter().any(|v| *v > 0.0)
+                && w.iter().any(|v| *v < 0.0)
+            {
+                ret.push((i, 8));
+            }
+        }
+    }
+
+    ret
+}
+
+pub struct SpcView {
+    chart: SpcChartType,
+    subgroup: usize,
+}
+
+impl SpcView {
+    pub fn default() -> Self {
+        SpcView {
+            chart: SpcChartType::IndividualsMR,
+            subgroup: 5,
+        }
+    }
+
+    fn draw_chart(
+        ui: &mut egui::Ui,
+        id: &str,
+        chart: &ControlChart,
+        violations: &[[f64; 2]],
+        unit: String,
+        height: f32,
+        reset: bool,
+    ) {
+        let mut plot = Plot::new(id)
+            .custom_x_axes(vec![
+                egui_plot::AxisHints::new_x().formatter(crate::x_formatter)
+            ])
+            .custom_y_axes(vec![egui_plot::AxisHints::new_y()
+                .formatter(crate::y_formatter)
+                .label(unit)])
+            .label_formatter(|name, value| {
+                if !name.is_empty() {
+                    format!("{}: {:+1.4E}", name, value.y)
+                } else {
+                    "".to_owned()
+                }
+            })
+            .link_axis("spc", true, false)
+            .height(height);
+
+        if reset {
+            plot = plot.reset();
+        }
+
+        plot.show(ui, |plot_ui| {
+            plot_ui
+                .line(Line::new(PlotPoints::from(chart.points.clone())).color(Color32::LIGHT_BLUE));
+            plot_ui.points(
+                Points::new(PlotPoints::from(chart.points.clone()))
+                    .color(Color32::BLUE)
+                    .radius(2.0),
+            );
+            if !violations.is_empty() {
+                plot_ui.points(
+                    Points::new(PlotPoints::from(violations.to_vec()))
+                        .color(Color32::RED)
+                        .radius(4.0)
+                        .name("Violation"),
+                );
+            }
+            plot_ui.hline(HLine::new(chart.center).color(Color32::GREEN).name("CL"));
+            plot_ui.hline(HLine::new(chart.ucl).color(Color32::RED).name("UCL"));
+            plot_ui.hline(HLine::new(chart.lcl).color(Color32::RED).name("LCL"));
+        });
+    }
+
+    // 'values' are the (time, measured value) pairs of the selected test and index
+    pub fn update(&mut self, ui: &mut egui::Ui, values: &[(u64, f32)], unit: String, reset: bool) {
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.chart, SpcChartType::IndividualsMR, "I-MR");
+            ui.selectable_value(&mut self.chart, SpcChartType::XbarR, "X-bar/R");
+
+            if self.chart == SpcChartType::XbarR {
+                ui.label("Subgroup:");
+                ui.add(
+                    egui::DragValue::new(&mut self.subgroup)
+                        .speed(1.0)
+                        .clamp_range(2..=10),
+                );
+            }
+        });
+
+        let Some(result) = compute_spc(values, self.chart, self.subgroup) else {
+            ui.label("Not enough measurements for the control chart!");
+            return;
+        };
+
+        ui.horizontal(|ui| {
+            ui.monospace(format!(
+                "CL: {:+1.4E}  UCL: {:+1.4E}  LCL: {:+1.4E}  σ: {:1.4E}",
+                result.main.center, result.main.ucl, result.main.lcl, result.sigma
+            ));
+        });
+
+        let mut rule_counts = [0usize; 8];
+        for (_, rule) in &result.violations {
+            rule_counts[rule - 1] += 1;
+        }
+
+        for (i, count) in rule_counts.iter().enumerate().filter(|(_, c)| **c > 0) {
+            ui.label(
+                egui::RichText::new(format!("Rule {}: {} - {}x", i + 1, RULES[i], count))
+                    .color(Color32::RED),
+            );
+        }
+
+        let mut violation_points: Vec<[f64; 2]> = result
+            .violations
+            .iter()
+            .map(|(i, _)| result.main.points[*i])
+            .collect();
+        violation_points.dedup();
+
+        let (main_label, range_label) = match self.chart {
+            SpcChartType::IndividualsMR => ("I", "MR"),
+            SpcChartType::XbarR => ("X-bar", "R"),
+        };
+
+        let height = (ui.available_height() - 40.0).max(100.0);
+        ui.label(main_label);
+        SpcView::draw_chart(
+            ui,
+            "SPC main",
+            &result.main,
+            &violation_points,
+            unit.clone(),
+            height * 0.65,
+            reset,
+        );
+        ui.label(range_label);
+        SpcView::draw_chart(
+            ui,
+            "SPC range",
+            &result.range,
+            &[],
+            unit,
+            height * 0.35 - 20.0,
+            reset,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(v: &[f32]) -> Vec<(u64, f32)> {
+        v.iter().enumerate().map(|(i, x)| (i as u64, *x)).collect()
+    }
+
+    fn points(v: &[f64]) -> Vec<[f64; 2]> {
+        v.iter().enumerate().map(|(i, x)| [i as f64, *x]).collect()
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    #[test]
+    fn individuals_mr_limits() {
+        let spc = compute_spc(
+            &values(&[10.0, 12.0, 11.0, 13.0, 12.0]),
+            SpcChartType::IndividualsMR,
+            0,
+        )
+        .unwrap();
+
+        // Moving ranges: 2, 1, 2, 1
+        let sigma = 1.5 / 1.128;
+        assert!(close(spc.sigma, sigma));
+        assert!(close(spc.main.center, 11.6));
+        assert!(close(spc.main.ucl, 11.6 + 3.0 * sigma));
+        assert!(close(spc.main.lcl, 11.6 - 3.0 * sigma));
+        assert_eq!(spc.range.points.len(), 4);
+        assert!(close(spc.range.ucl, 3.267 * 1.5));
+        assert!(close(spc.range.lcl, 0.0));
+    }
+
+    #[test]
+    fn xbar_r_subgroups() {
+        let spc = compute_spc(&values(&[1.0, 3.0, 2.0, 4.0, 5.0]), SpcChartType::XbarR, 2).unwrap();
+
+        // The incomplete last subgroup is dropped
+        assert_eq!(spc.main.points, vec![[1.0, 2.0], [3.0, 3.0]]);
+        assert!(close(spc.main.center, 2.5));
+        assert!(close(spc.sigma, 1.880 * 2.0 / 3.0));
+        assert!(close(spc.range.center, 2.0));
+        assert!(close(spc.range.ucl, 3.267 * 2.0));
+    }
+
+    #[test]
+    fn too_few_values() {
+        assert!(compute_spc(&values(&[1.0]), SpcChartType::IndividualsMR, 0).is_none());
+        assert!(compute_spc(&values(&[1.0, 2.0, 3.0]), SpcChartType::XbarR, 2).is_none());
+    }
+
+    #[test]
+    fn outlier_breaks_rule_1() {
+        let mut v: Vec<f32> = (0..19).map(|i| (i % 2) as f32).collect();
+        v.push(100.0);
+
+        let spc = compute_spc(&values(&v), SpcChartType::IndividualsMR, 0).unwrap();
+        assert!(spc.violations.contains(&(19, 1)));
+        assert!(!spc.violations.iter().any(|v| v.1 == 1 && v.0 != 19));
+    }
+
+    #[test]
+    fn nelson_rule_patterns() {
+        // 9 points above the center line
+        assert!(nelson_rules(&points(&[0.5; 9]), 0.0, 1.0).contains(&(8, 2)));
+        assert!(!nelson_rules(&points(&[0.5; 8]), 0.0, 1.0).contains(&(7, 2)));
+
+        // 6 points increasing
+        let trend = nelson_rules(&points(&[0.1, 0.2, 0.3, 0.4, 0.5, 0.6]), 0.0, 1.0);
+        assert!(trend.contains(&(5, 3)));
+
+        // 2 of 3 beyond 2σ on the same side
+        let rule_5 = nelson_rules(&points(&[2.5, 0.0, 2.5]), 0.0, 1.0);
+        assert_eq!(rule_5, vec![(2, 5)]);
+
+        // ... but not on opposite sides
+        assert!(nelson_rules(&points(&[2.5, 0.0, -2.5]), 0.0, 1.0).is_empty());
+
+        // No rules without spread
+        assert!(nelson_rules(&points(&[5.0; 20]), 5.0, 0.0).is_empty());
+    }
+}
@@ -0,0 +1,187 @@
+use rust_xlsxwriter::*;
+use std::path::PathBuf;
+
+use crate::{LogFileHandler, TLimit, TResult};
+
+// Measurements closer to a limit than this fraction of the tolerance band count as "near the limit"
+const NEAR_LIMIT: f64 = 0.1;
+
+pub struct TestStatistics {
+    pub count: usize,
+    pub mean: f64,
+    pub std_dev: f64,
+    pub min: f64,
+    pub max: f64,
+    pub limits: Option<(f64, f64)>, // (LL, UL)
+    pub cp: Option<f64>,
+    pub cpk: Option<f64>,
+    pub near_limits: Option<f64>, // %
+}
+
+// Limits of the last measurement on the index. If the limits changed, then this is the newest one.
+fn get_limits(results: &[(u64, usize, TResult, TLimit)], index: usize) -> Option<(f64, f64)> {
+    results
+        .iter()
+        .filter(|r| index == 0 || index == r.1)
+        .max_by_key(|r| r.0)
+        .and_then(|r| match &r.3 {
+            TLimit::Lim3(_, ul, ll) | TLimit::Lim2(ul, ll) => Some((*ll as f64, *ul as f64)),
+            _ => None,
+        })
+}
+
+// Statistics of the test on the index (0 = all), None if there are no measurements
+pub fn compute_statistics(
+    results: &[(u64, usize, TResult, TLimit)],
+    index: usize,
+) -> Option<TestStatistics> {
+    let values: Vec<f64> = crate::get_selected_values(results, index)
+        .iter()
+        .map(|v| v.1 as f64)
+        .collect();
+
+    if values.is_empty() {
+        return None;
+    }
+
+    let count = values.len();
+    let mean = values.iter().sum::<f64>() / count as f64;
+    let std_dev = if count > 1 {
+        (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (count - 1) as f64).sqrt()
+    } else {
+        0.0
+    };
+    let min = values.iter().cloned().fold(f64::MAX, f64::min);
+    let max = values.iter().cloned().fold(f64::MIN, f64::max);
+
+    let limits = get_limits(results, index);
+    let mut cp = None;
+    let mut cpk = None;
+    let mut near_limits = None;
+
+    if let Some((ll, ul)) = limits {
+        if std_dev > 0.0 {
+            cp = Some((ul - ll) / (6.0 * std_dev));
+            cpk = Some((ul - mean).min(mean - ll) / (3.0 * std_dev));
+        }
+
+        let margin = (ul - ll) * NEAR_LIMIT;
+        let near = values
+            .iter()
+            .filter(|v| **v < ll + margin || **v > ul - margin)
+            .count();
+        near_limits = Some(near as f64 / count as f64 * 100.0);
+    }
+
+    Some(TestStatistics {
+        count,
+        mean,
+        std_dev,
+        min,
+        max,
+        limits,
+        cp,
+        cpk,
+        near_limits,
+    })
+}
+
+fn write_option(worksheet: &mut Worksheet, row: u32, col: u16, x: Option<f64>) -> Result<(), XlsxError> {
+    if let Some(x) = x {
+        worksheet.write_number(row, col, x)?;
+    }
+
+    Ok(())
+}
+
+// Writes the statistics of every test to a "Statistics" worksheet.
+pub fn export_statistics(path: PathBuf, lfh: &LogFileHandler) -> Result<(), XlsxError> {
+    let header_format = Format::new().set_bold().set_border_bottom(FormatBorder::Thin);
+    let mut workbook = Workbook::new();
+    let worksheet = workbook.add_worksheet().set_name("Statistics")?;
+
+    let header = [
+        "Test", "Unit", "Count", "Mean", "Std. dev.", "Min", "Max", "LL", "UL", "Cp", "Cpk",
+        "Near limits (%)",
+    ];
+    for (col, name) in header.iter().enumerate() {
+        worksheet.write_with_format(0, col as u16, *name, &header_format)?;
+    }
+    worksheet.set_column_width_pixels(0, 200)?;
+    worksheet.set_freeze_panes(1, 1)?;
+
+    let mut row: u32 = 1;
+    for (i, (name, ttype)) in lfh.get_testlist().iter().enumerate() {
+        let results = lfh.get_stats_for_test(i);
+        let Some(stats) = compute_statistics(&results.1, 0) else {
+            continue;
+        };
+
+        worksheet.write(row, 0, name)?;
+        worksheet.write(row, 1, ttype.unit())?;
+        worksheet.write(row, 2, stats.count as u32)?;
+        worksheet.write(row, 3, stats.mean)?;
+        worksheet.write(row, 4, stats.std_dev)?;
+        worksheet.write(row, 5, stats.min)?;
+        worksheet.write(row, 6, stats.max)?;
+        write_option(worksheet, row, 7, stats.limits.map(|l| l.0))?;
+        write_option(worksheet, row, 8, stats.limits.map(|l| l.1))?;
+        write_option(worksheet, row, 9, stats.cp)?;
+        write_option(worksheet, row, 10, stats.cpk)?;
+        write_option(worksheet, row, 11, stats.near_limits)?;
+        row += 1;
+    }
+
+    worksheet.autofilter(0, 0, row.max(1) - 1, 11)?;
+    workbook.save(path)?;
+
+    Ok(())
+}
+
+// Statistics panel, shown next to the plot
+pub fn statistics_ui(ui: &mut egui::Ui, stats: &Option<TestStatistics>, lang: usize) {
+    let Some(stats) = stats else {
+        return;
+    };
+
+    let cpk_color = match stats.cpk {
+        Some(x) if x < 1.0 => egui::Color32::RED,
+        Some(x) if x < 1.33 => egui::Color32::GOLD,
+        _ => ui.visuals().text_color(),
+    };
+    let print = |x: Option<f64>| x.map_or("-".to_string(), |x| format!("{:.2}", x));
+
+    egui::Grid::new("test statistics").striped(true).show(ui, |ui| {
+        ui.monospace("n");
+        ui.monospace(format!("{}", stats.count));
+        ui.end_row();
+
+        ui.monospace(crate::MESSAGE_P[crate::MEAN][lang]);
+        ui.monospace(format!("{:+1.4E}", stats.mean));
+        ui.end_row();
+
+        ui.monospace("σ");
+        ui.monospace(format!("{:1.4E}", stats.std_dev));
+        ui.end_row();
+
+        ui.monospace("Min");
+        ui.monospace(format!("{:+1.4E}", stats.min));
+        ui.end_row();
+
+        ui.monospace("Max");
+        ui.monospace(format!("{:+1.4E}", stats.max));
+        ui.end_row();
+
+        ui.monospace("Cp");
+        ui.monospace(print(stats.cp));
+        ui.end_row();
+
+        ui.monospace("Cpk");
+        ui.label(egui::RichText::new(print(stats.cpk)).monospace().color(cpk_color));
+        ui.end_row();
+
+        ui.monospace(crate::MESSAGE_P[crate::NEAR_LIMITS][lang]);
+        ui.monospace(stats.near_limits.map_or("-".to_string(), |x| format!("{:.2}%", x)));
+        ui.end_row();
+    });
+}
@@ -0,0 +1,270 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+use printpdf::*;
+
+use crate::{format_limits, format_value, u64_to_datetime, Attempt, BResult};
+
+/*
+Failure ticket, a one page PDF traveling with the board to the repair station.
+It has the DMC, panel and index of the board, the time of every attempt,
+and the failing tests of the failed attempts with the measured values and the limits.
+A monospace font is used, so the columns line up without measuring the text.
+The built-in Courier only has the Windows-1252 characters, printpdf drops the rest,
+so the text is mapped to those first (e.g. "Ω" -> "Ohm").
+*/
+
+const PAGE_WIDTH: f32 = 210.0; // A4, mm
+const PAGE_HEIGHT: f32 = 297.0;
+const MARGIN: f32 = 15.0;
+const LINE_HEIGHT: f32 = 4.5;
+const FONT_SIZE: f32 = 9.0;
+
+fn time_text(t: u64) -> String {
+    u64_to_datetime(t).map_or(t.to_string(), |t| t.format("%F %T").to_string())
+}
+
+// Characters of Windows-1252 between 0x80 and 0x9F, the rest of it is the same as Latin-1
+const CP1252_EXTRA: &str = "€‚ƒ„…†‡ˆ‰Š‹ŒŽ‘’“”•–—˜™š›œžŸ";
+
+// Replaces the characters Courier can't print. Unknown ones become '?',
+// so they still take up their place in the columns.
+fn pdf_text(text: &str) -> String {
+    let mut ret = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            ' '..='~' | '\u{A0}'..='\u{FF}' => ret.push(c),
+            _ if CP1252_EXTRA.contains(c) => ret.push(c),
+            'Ω' | '\u{2126}' => ret.push_str("Ohm"),
+            'μ' => ret.push('µ'),
+            'ő' => ret.push('ö'),
+            'ű' => ret.push('ü'),
+            'Ő' => ret.push('Ö'),
+            'Ű' => ret.push('Ü'),
+            _ => ret.push('?'),
+        }
+    }
+    ret
+}
+
+// Widths of the test, value columns in characters, the limits get the rest of the line
+const NAME_WIDTH: usize = 30;
+const VALUE_WIDTH: usize = 22;
+
+// Pads 'text' to 'width' characters
+fn column(text: &str, width: usize) -> String {
+    format!("{:<width$}", text)
+}
+
+// Splits 'text' into lines of 'width' - 1 characters, leaving a space before the next column.
+// Names are never cut, as two components with the same beginning would look the same.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    if chars.is_empty() {
+        return vec![String::new()];
+    }
+
+    chars
+        .chunks(width - 1)
+        .map(|c| c.iter().collect())
+        .collect()
+}
+
+struct TicketWriter {
+    doc: PdfDocumentReference,
+    layer: PdfLayerReference,
+    font: IndirectFontRef,
+    bold: IndirectFontRef,
+    y: f32,
+}
+
+impl TicketWriter {
+    fn new(title: &str) -> Result<Self, printpdf::Error> {
+        let (doc, page, layer) =
+            PdfDocument::new(title, Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+        let font = doc.add_builtin_font(BuiltinFont::Courier)?;
+        let bold = doc.add_builtin_font(BuiltinFont::CourierBold)?;
+        let layer = doc.get_page(page).get_layer(layer);
+
+        Ok(TicketWriter {
+            doc,
+            layer,
+            font,
+            bold,
+            y: PAGE_HEIGHT - MARGIN,
+        })
+    }
+
+    // Continues on a new page, if the current one is full
+    fn line(&mut self, text: &str, size: f32, bold: bool) {
+        if self.y < MARGIN {
+            let (page, layer) = self
+                .doc
+                .add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+            self.layer = self.doc.get_page(page).get_layer(layer);
+            self.y = PAGE_HEIGHT - MARGIN;
+        }
+
+        let font = if bold { &self.bold } else { &self.font };
+        self.layer
+            .use_text(pdf_text(text), size, Mm(MARGIN), Mm(self.y), font);
+        self.y -= LINE_HEIGHT * size / FONT_SIZE;
+    }
+
+    fn gap(&mut self) {
+        self.y -= LINE_HEIGHT;
+    }
+}
+
+// 'attempts' are the attempts of one board, ordered by time
+pub fn write_ticket(path: &Path, attempts: &[Attempt]) -> Result<(), printpdf::Error> {
+    let Some(last) = attempts.last() else {
+        return Ok(());
+    };
+
+    let mut w = TicketWriter::new(&last.DMC)?;
+
+    w.line(&last.DMC, 16.0, true);
+    w.gap();
+    w.line(&format!("Panel:   {}", last.DMC_mb), FONT_SIZE, false);
+    w.line(&format!("Index:   {}", last.index), FONT_SIZE, false);
+    w.line(
+        &format!("Result:  {}", last.result.print()),
+        FONT_SIZE,
+        true,
+    );
+    w.line(
+        &format!("Printed: {}", Local::now().format("%F %T")),
+        FONT_SIZE,
+        false,
+    );
+    w.gap();
+
+    w.line("Attempts:", FONT_SIZE, true);
+    for (i, a) in attempts.iter().enumerate() {
+        w.line(
+            &format!(
+                "{:>3}. {} - {}  {}",
+                i + 1,
+                time_text(a.time_start),
+                time_text(a.time_end),
+                a.result.print()
+            ),
+            FONT_SIZE,
+            false,
+        );
+    }
+
+    for (i, a) in attempts.iter().enumerate() {
+        if a.result != BResult::Fail {
+            continue;
+        }
+
+        w.gap();
+        w.line(
+            &format!(
+                "Failing tests, attempt {} ({}):",
+                i + 1,
+                time_text(a.time_start)
+            ),
+            FONT_SIZE,
+            true,
+        );
+        w.line(
+            &format!(
+                "{}{}{}",
+                column("Test", NAME_WIDTH),
+                column("Value", VALUE_WIDTH),
+                "Limits"
+            ),
+            FONT_SIZE,
+            true,
+        );
+
+        for test in a.tests.iter().filter(|t| t.result == BResult::Fail) {
+            // Mapped before the padding, as it can change the length
+            let name = wrap(&pdf_text(&test.name), NAME_WIDTH);
+            let value = pdf_text(&format_value(test.value, &test.ttype));
+            w.line(
+                &format!(
+                    "{}{}{}",
+                    column(&name[0], NAME_WIDTH),
+                    column(&value, VALUE_WIDTH),
+                    format_limits(&test.limits, &test.ttype)
+                ),
+                FONT_SIZE,
+                false,
+            );
+            for rest in &name[1..] {
+                w.line(rest, FONT_SIZE, false);
+            }
+        }
+    }
+
+    w.doc.save(&mut BufWriter::new(File::create(path)?))
+}
+
+// Asks for the path, writes the ticket and opens it for printing
+pub fn print_ticket(attempts: &[Attempt]) {
+    let Some(last) = attempts.last() else {
+        return;
+    };
+
+    let Some(path): Option<PathBuf> = rfd::FileDialog::new()
+        .add_filter("PDF", &["pdf"])
+        .set_file_name(format!("{}.pdf", last.DMC))
+        .save_file()
+    else {
+        return;
+    };
+
+    if let Err(e) = write_ticket(&path, attempts) {
+        println!("ERR: Failed to write the ticket {}: {e}", path.display());
+        return;
+    }
+
+    if let Err(e) = open_file(&path) {
+        println!("ERR: Failed to open the ticket: {e}");
+    }
+}
+
+// Opens the file in the default viewer of the platform
+fn open_file(path: &Path) -> std::io::Result<std::process::Child> {
+    #[cfg(windows)]
+    let command = std::process::Command::new("cmd")
+        .args(["/C", "start", ""])
+        .arg(path)
+        .spawn();
+
+    #[cfg(target_os = "macos")]
+    let command = std::process::Command::new("open").arg(path).spawn();
+
+    #[cfg(not(any(windows, target_os = "macos")))]
+    let command = std::process::Command::new("xdg-open").arg(path).spawn();
+
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_is_mapped_to_cp1252() {
+        assert_eq!(pdf_text("1.0000E3 Ω"), "1.0000E3 Ohm");
+        assert_eq!(
+            pdf_text("1.0000E-6 µF 1.0000E-6 μF"),
+            "1.0000E-6 µF 1.0000E-6 µF"
+        );
+        assert_eq!(pdf_text("Fűtés – 25°C"), "Fütés – 25°C");
+        assert_eq!(pdf_text("R1 电阻"), "R1 ??");
+    }
+
+    #[test]
+    fn mapped_text_is_kept() {
+        let text = pdf_text("Ω μ ő 电");
+        assert_eq!(pdf_text(&text), text);
+    }
+}
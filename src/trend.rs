@@ -0,0 +1,193 @@
+use std::collections::{BTreeMap, HashSet};
+
+use chrono::NaiveDateTime;
+use egui::Color32;
+use egui_plot::{Legend, Line, Plot, PlotPoints, Points};
+
+use crate::{u64_to_datetime, FailureList, HourlyStats, ShiftModel, TimeBucket};
+
+/*
+Failure trend of the selected tests, as the number of failures or the failure rate per hour, shift or day.
+The failures come from the timestamps in FailureList.failed, the number of tested boards
+from the hourly statistics, both are summed into the buckets by the start of the hour.
+*/
+
+const COLORS: [Color32; 6] = [
+    Color32::RED,
+    Color32::BLUE,
+    Color32::DARK_GREEN,
+    Color32::GOLD,
+    Color32::LIGHT_BLUE,
+    Color32::BROWN,
+];
+
+pub struct TrendView {
+    bucket: TimeBucket,
+    rate: bool,
+    selected: Option<HashSet<(usize, String)>>, // (product, test name), None = the top 3 failures
+}
+
+impl TrendView {
+    pub fn default() -> Self {
+        TrendView {
+            bucket: TimeBucket::Hour,
+            rate: false,
+            selected: None,
+        }
+    }
+
+    // Called when new logs are loaded, so the selection goes back to the top failures
+    pub fn reset(&mut self) {
+        self.selected = None;
+    }
+
+    // 'names' are the product names, only shown when there are more than one.
+    pub fn update(
+        &mut self,
+        ui: &mut egui::Ui,
+        failures: &[(usize, FailureList)],
+        hourly: &[HourlyStats],
+        shifts: &ShiftModel,
+        names: &[String],
+        lang: usize,
+    ) {
+        let selected = self.selected.get_or_insert_with(|| {
+            failures
+                .iter()
+                .take(3)
+                .map(|f| (f.0, f.1.name.clone()))
+                .collect()
+        });
+
+        ui.horizontal(|ui| {
+            ui.selectable_value(
+                &mut self.bucket,
+                TimeBucket::Hour,
+                crate::MESSAGE_F[crate::HOUR_B][lang],
+            );
+            ui.selectable_value(
+                &mut self.bucket,
+                TimeBucket::Shift,
+                crate::MESSAGE[crate::SHIFT][lang],
+            );
+            ui.selectable_value(
+                &mut self.bucket,
+                TimeBucket::Day,
+                crate::MESSAGE_F[crate::DAY_B][lang],
+            );
+
+            ui.separator();
+            ui.selectable_value(&mut self.rate, false, crate::MESSAGE_F[crate::COUNT][lang]);
+            ui.selectable_value(&mut self.rate, true, crate::MESSAGE_F[crate::RATE][lang]);
+        });
+
+        let label = |product: usize, name: &str| match names.get(product) {
+            Some(p) if names.len() > 1 => format!("{} ({})", name, p),
+            _ => name.to_string(),
+        };
+
+        // Test selection
+        egui::SidePanel::left("trend tests")
+            .resizable(true)
+            .show_inside(ui, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for (product, f) in failures {
+                        let key = (*product, f.name.clone());
+                        let mut checked = selected.contains(&key);
+                        if ui
+                            .checkbox(
+                                &mut checked,
+                                format!("{} - {}", label(*product, &f.name), f.total),
+                            )
+                            .changed()
+                        {
+                            if checked {
+                                selected.insert(key);
+                            } else {
+                                selected.remove(&key);
+                            }
+                        }
+                    }
+                });
+            });
+
+        // Tested boards per bucket, every bucket with tests is shown, even without failures
+        let mut tested: BTreeMap<NaiveDateTime, usize> = BTreeMap::new();
+        for hour in hourly {
+            if let Some(t) = u64_to_datetime(hour.0 * 10000) {
+                *tested
+                    .entry(shifts.bucket_start(t, self.bucket))
+                    .or_default() += hour.1 + hour.2;
+            }
+        }
+
+        let mut series: Vec<(String, Vec<[f64; 2]>)> = Vec::new();
+        for (product, f) in failures {
+            if !selected.contains(&(*product, f.name.clone())) {
+                continue;
+            }
+
+            let mut counts: BTreeMap<NaiveDateTime, usize> =
+                tested.keys().map(|k| (*k, 0)).collect();
+            for (_, t) in &f.failed {
+                if let Some(t) = u64_to_datetime(*t) {
+                    *counts
+                        .entry(shifts.bucket_start(t, self.bucket))
+                        .or_default() += 1;
+                }
+            }
+
+            let points = counts
+                .iter()
+                .map(|(t, c)| {
+                    let y = if self.rate {
+                        match tested.get(t) {
+                            Some(n) if *n > 0 => *c as f64 / *n as f64 * 100.0,
+                            _ => 0.0,
+                        }
+                    } else {
+                        *c as f64
+                    };
+                    [t.and_utc().timestamp() as f64, y]
+                })
+                .collect();
+
+            series.push((label(*product, &f.name), points));
+        }
+
+        Plot::new("Failure trend")
+            .custom_x_axes(vec![
+                egui_plot::AxisHints::new_x().formatter(crate::x_formatter)
+            ])
+            .custom_y_axes(vec![egui_plot::AxisHints::new_y().label(if self.rate {
+                crate::MESSAGE_F[crate::RATE][lang]
+            } else {
+                crate::MESSAGE_F[crate::COUNT][lang]
+            })])
+            .label_formatter(|name, value| {
+                if !name.is_empty() {
+                    format!("{}: {:.2}", name, value.y)
+                } else {
+                    String::new()
+                }
+            })
+            .legend(Legend::default())
+            .include_y(0.0)
+            .show(ui, |plot_ui| {
+                for (i, (name, points)) in series.into_iter().enumerate() {
+                    let color = COLORS[i % COLORS.len()];
+                    plot_ui.line(
+                        Line::new(PlotPoints::from(points.clone()))
+                            .color(color)
+                            .name(&name),
+                    );
+                    plot_ui.points(
+                        Points::new(PlotPoints::from(points))
+                            .color(color)
+                            .radius(3.0)
+                            .name(&name),
+                    );
+                }
+            });
+    }
+}
@@ -0,0 +1,470 @@
+use std::collections::{BTreeMap, HashSet};
+use std::sync::{Arc, RwLock};
+
+use chrono::NaiveDateTime;
+use egui::Color32;
+use egui_extras::{Column, TableBuilder};
+use egui_plot::{Legend, Line, Plot, PlotPoints, Points};
+
+use crate::{u64_to_datetime, LogFile, LogFileHandler, ShiftModel, TimeBucket};
+
+/*
+Fixture wear tracking. The failures are grouped by the board index and the family of the test.
+Pins and shorts failures are usually caused by the fixture (worn or dirty probes),
+the analog families show it too, but mixed with real component failures.
+For each index and family it counts the boards with at least one failure of the family,
+per hour, shift or day. If the failure rate of the last buckets is well above the earlier rate,
+then it gives a warning, like "index 7 pins failures rising since Tuesday".
+*/
+
+// Only warn if there were at least this many failing boards since the rise started
+const MIN_FAILS: usize = 3;
+// and at least this many boards were tested before it, so the earlier rate means something
+const MIN_TESTED_BEFORE: usize = 20;
+// Recent rate has to be at least this many times the earlier rate
+const RISE_FACTOR: f64 = 2.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum TestFamily {
+    Pins,
+    Shorts,
+    Capacitor,
+    Resistor,
+    Voltage,
+    Other,
+}
+
+impl TestFamily {
+    pub const ALL: [TestFamily; 6] = [
+        TestFamily::Pins,
+        TestFamily::Shorts,
+        TestFamily::Capacitor,
+        TestFamily::Resistor,
+        TestFamily::Voltage,
+        TestFamily::Other,
+    ];
+
+    pub fn of(test: &str) -> Self {
+        let test = test.to_lowercase();
+        if test.starts_with("pins") {
+            TestFamily::Pins
+        } else if test.starts_with("shorts") {
+            TestFamily::Shorts
+        } else {
+            match test.chars().next() {
+                Some('c') => TestFamily::Capacitor,
+                Some('r') => TestFamily::Resistor,
+                Some('v') => TestFamily::Voltage,
+                _ => TestFamily::Other,
+            }
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TestFamily::Pins => "pins",
+            TestFamily::Shorts => "shorts",
+            TestFamily::Capacitor => "c",
+            TestFamily::Resistor => "r",
+            TestFamily::Voltage => "v",
+            TestFamily::Other => "other",
+        }
+    }
+
+    // Pins and shorts failures are the most likely to come from the fixture
+    pub fn fixture_related(&self) -> bool {
+        matches!(self, TestFamily::Pins | TestFamily::Shorts)
+    }
+}
+
+pub struct WearSeries {
+    pub index: usize,
+    pub family: TestFamily,
+    pub points: Vec<(NaiveDateTime, usize, usize)>, // (bucket, failing boards, tested boards)
+}
+
+pub struct WearWarning {
+    pub index: usize,
+    pub family: TestFamily,
+    pub since: NaiveDateTime,
+    pub before: f64, // failure rate before 'since', %
+    pub after: f64,  // failure rate since 'since', %
+}
+
+pub struct WearAnalysis {
+    pub series: Vec<WearSeries>,
+    pub warnings: Vec<WearWarning>,
+}
+
+fn rate(fails: usize, tested: usize) -> f64 {
+    if tested == 0 {
+        0.0
+    } else {
+        fails as f64 / tested as f64 * 100.0
+    }
+}
+
+// Finds the start of the last run of buckets, where the rate is above the overall rate,
+// and gives a warning if that run is a significant rise.
+fn check_rise(series: &WearSeries) -> Option<WearWarning> {
+    let points: Vec<&(NaiveDateTime, usize, usize)> =
+        series.points.iter().filter(|p| p.2 > 0).collect();
+    if points.len() < 3 {
+        return None;
+    }
+
+    let total_fails: usize = points.iter().map(|p| p.1).sum();
+    let total_tested: usize = points.iter().map(|p| p.2).sum();
+    let overall = rate(total_fails, total_tested);
+
+    let mut start = points.len();
+    while start > 0 && rate(points[start - 1].1, points[start - 1].2) > overall {
+        start -= 1;
+    }
+    if start == 0 || start == points.len() {
+        return None;
+    }
+
+    let (before, after) = points.split_at(start);
+    let after_fails: usize = after.iter().map(|p| p.1).sum();
+    let before_tested: usize = before.iter().map(|p| p.2).sum();
+    let before_rate = rate(before.iter().map(|p| p.1).sum(), before_tested);
+    let after_rate = rate(after_fails, after.iter().map(|p| p.2).sum());
+
+    if after_fails >= MIN_FAILS
+        && before_tested >= MIN_TESTED_BEFORE
+        && after_rate >= before_rate * RISE_FACTOR
+    {
+        Some(WearWarning {
+            index: series.index,
+            family: series.family,
+            since: after[0].0,
+            before: before_rate,
+            after: after_rate,
+        })
+    } else {
+        None
+    }
+}
+
+pub fn analyze_wear(logs: &[&LogFile], bucket: TimeBucket, shifts: &ShiftModel) -> WearAnalysis {
+    let mut tested: BTreeMap<(usize, NaiveDateTime), usize> = BTreeMap::new();
+    let mut fails: BTreeMap<(usize, TestFamily, NaiveDateTime), usize> = BTreeMap::new();
+
+    for log in logs {
+        let Some(t) = u64_to_datetime(log.get_time_start()) else {
+            continue;
+        };
+        let t = shifts.bucket_start(t, bucket);
+        let index = log.get_index();
+
+        *tested.entry((index, t)).or_default() += 1;
+
+        let families: HashSet<TestFamily> = log
+            .get_failed_tests()
+            .iter()
+            .map(|t| TestFamily::of(t))
+            .collect();
+        for family in families {
+            *fails.entry((index, family, t)).or_default() += 1;
+        }
+    }
+
+    let mut pairs: Vec<(usize, TestFamily)> = fails.keys().map(|k| (k.0, k.1)).collect();
+    pairs.dedup();
+
+    let series: Vec<WearSeries> = pairs
+        .into_iter()
+        .map(|(index, family)| WearSeries {
+            index,
+            family,
+            points: tested
+                .range((index, NaiveDateTime::MIN)..=(index, NaiveDateTime::MAX))
+                .map(|((_, t), n)| (*t, *fails.get(&(index, family, *t)).unwrap_or(&0), *n))
+                .collect(),
+        })
+        .collect();
+
+    let mut warnings: Vec<WearWarning> = series.iter().filter_map(check_rise).collect();
+    warnings.sort_by(|a, b| {
+        b.family
+            .fixture_related()
+            .cmp(&a.family.fixture_related())
+            .then(b.after.total_cmp(&a.after))
+    });
+
+    WearAnalysis { series, warnings }
+}
+
+// Failing boards per index and family, the same counts as the series
+fn family_matrix(series: &[WearSeries]) -> BTreeMap<usize, [usize; 6]> {
+    let mut ret: BTreeMap<usize, [usize; 6]> = BTreeMap::new();
+
+    for s in series {
+        let fails: usize = s.points.iter().map(|p| p.1).sum();
+        ret.entry(s.index).or_default()[s.family as usize] += fails;
+    }
+
+    ret
+}
+
+pub struct WearView {
+    bucket: TimeBucket,
+    selected: Option<(usize, TestFamily)>,
+    analysis: Option<WearAnalysis>,
+}
+
+impl WearView {
+    pub fn default() -> Self {
+        WearView {
+            bucket: TimeBucket::Day,
+            selected: None,
+            analysis: None,
+        }
+    }
+
+    // Has to be called when the logs change
+    pub fn reset(&mut self) {
+        self.analysis = None;
+    }
+
+    pub fn update(
+        &mut self,
+        ui: &mut egui::Ui,
+        handlers: &[Arc<RwLock<LogFileHandler>>],
+        shifts: &ShiftModel,
+        lang: usize,
+    ) {
+        ui.horizontal(|ui| {
+            let old = self.bucket;
+            ui.selectable_value(
+                &mut self.bucket,
+                TimeBucket::Hour,
+                crate::MESSAGE_F[crate::HOUR_B][lang],
+            );
+            ui.selectable_value(
+                &mut self.bucket,
+                TimeBucket::Shift,
+                crate::MESSAGE[crate::SHIFT][lang],
+            );
+            ui.selectable_value(
+                &mut self.bucket,
+                TimeBucket::Day,
+                crate::MESSAGE_F[crate::DAY_B][lang],
+            );
+            if old != self.bucket {
+                self.analysis = None;
+            }
+        });
+
+        let bucket = self.bucket;
+        let analysis = self.analysis.get_or_insert_with(|| {
+            let locks: Vec<_> = handlers.iter().map(|h| h.read().unwrap()).collect();
+            let logs: Vec<&LogFile> = locks.iter().flat_map(|l| l.get_logs()).collect();
+            analyze_wear(&logs, bucket, shifts)
+        });
+
+        let time_format = if bucket == TimeBucket::Day {
+            "%A (%m-%d)"
+        } else {
+            "%m-%d %H:%M"
+        };
+
+        // Report
+        ui.separator();
+        if analysis.warnings.is_empty() {
+            ui.label(crate::MESSAGE_F[crate::NO_WEAR][lang]);
+        }
+        for w in &analysis.warnings {
+            let color = if w.family.fixture_related() {
+                Color32::RED
+            } else {
+                Color32::GOLD
+            };
+            let text = format!(
+                "{} {}: {} {} {} ({:.2}% -> {:.2}%)",
+                crate::MESSAGE_F[crate::INDEX][lang],
+                w.index,
+                w.family.name(),
+                crate::MESSAGE_F[crate::WEAR_RISING][lang],
+                w.since.format(time_format),
+                w.before,
+                w.after
+            );
+            if ui
+                .add(
+                    egui::Label::new(egui::RichText::new(text).color(color))
+                        .sense(egui::Sense::click()),
+                )
+                .clicked()
+            {
+                self.selected = Some((w.index, w.family));
+            }
+        }
+        ui.separator();
+
+        // Failing boards by index and family, clicking a cell selects it for the plot
+        ui.label(crate::MESSAGE_F[crate::WEAR_MATRIX][lang]);
+        let matrix = family_matrix(&analysis.series);
+        let max = matrix.values().flatten().max().copied().unwrap_or(1).max(1);
+        ui.push_id("wear matrix", |ui| {
+            TableBuilder::new(ui)
+                .striped(true)
+                .column(Column::initial(50.0))
+                .columns(Column::initial(60.0), TestFamily::ALL.len())
+                .max_scroll_height(200.0)
+                .header(20.0, |mut header| {
+                    header.col(|ui| {
+                        ui.strong(crate::MESSAGE_F[crate::INDEX][lang]);
+                    });
+                    for family in TestFamily::ALL {
+                        header.col(|ui| {
+                            ui.strong(family.name());
+                        });
+                    }
+                })
+                .body(|mut body| {
+                    for (index, counts) in &matrix {
+                        body.row(16.0, |mut row| {
+                            row.col(|ui| {
+                                ui.label(format!("{}.", index));
+                            });
+                            for (family, n) in TestFamily::ALL.iter().zip(counts) {
+                                row.col(|ui| {
+                                    let color = Color32::RED
+                                        .gamma_multiply(*n as f32 / max as f32)
+                                        .gamma_multiply(0.8);
+                                    ui.painter().rect_filled(ui.max_rect(), 0.0, color);
+                                    if ui
+                                        .add(
+                                            egui::Label::new(format!("{}", n))
+                                                .sense(egui::Sense::click()),
+                                        )
+                                        .clicked()
+                                    {
+                                        self.selected = Some((*index, *family));
+                                    }
+                                });
+                            }
+                        });
+                    }
+                });
+        });
+        ui.separator();
+
+        // Failure rate of the selected index and family over time
+        let Some((index, family)) = self.selected else {
+            return;
+        };
+        let Some(series) = analysis
+            .series
+            .iter()
+            .find(|s| s.index == index && s.family == family)
+        else {
+            return;
+        };
+
+        let name = format!(
+            "{} {}: {}",
+            crate::MESSAGE_F[crate::INDEX][lang],
+            index,
+            family.name()
+        );
+        let points: Vec<[f64; 2]> = series
+            .points
+            .iter()
+            .map(|p| [p.0.and_utc().timestamp() as f64, rate(p.1, p.2)])
+            .collect();
+
+        Plot::new("Wear")
+            .custom_x_axes(vec![
+                egui_plot::AxisHints::new_x().formatter(crate::x_formatter)
+            ])
+            .custom_y_axes(vec![
+                egui_plot::AxisHints::new_y().label(crate::MESSAGE_F[crate::RATE][lang])
+            ])
+            .legend(Legend::default())
+            .include_y(0.0)
+            .show(ui, |plot_ui| {
+                plot_ui.line(
+                    Line::new(PlotPoints::from(points.clone()))
+                        .color(Color32::RED)
+                        .name(&name),
+                );
+                plot_ui.points(
+                    Points::new(PlotPoints::from(points))
+                        .color(Color32::RED)
+                        .radius(3.0)
+                        .name(&name),
+                );
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn series(points: &[(usize, usize)]) -> WearSeries {
+        let day = |d: usize| {
+            NaiveDate::from_ymd_opt(2024, 3, d as u32 + 1)
+                .unwrap()
+                .and_hms_opt(6, 0, 0)
+                .unwrap()
+        };
+
+        WearSeries {
+            index: 7,
+            family: TestFamily::Pins,
+            points: points
+                .iter()
+                .enumerate()
+                .map(|(d, (fails, tested))| (day(d), *fails, *tested))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_families() {
+        assert_eq!(TestFamily::of("PINS_1"), TestFamily::Pins);
+        assert_eq!(TestFamily::of("shorts"), TestFamily::Shorts);
+        assert_eq!(TestFamily::of("c12"), TestFamily::Capacitor);
+        assert_eq!(TestFamily::of("R3%1"), TestFamily::Resistor);
+        assert_eq!(TestFamily::of("v_5v"), TestFamily::Voltage);
+        assert_eq!(TestFamily::of("d4"), TestFamily::Other);
+        assert_eq!(TestFamily::of(""), TestFamily::Other);
+    }
+
+    #[test]
+    fn rising_rate_is_reported() {
+        let s = series(&[(1, 50), (0, 50), (1, 50), (5, 50), (6, 50)]);
+        let warning = check_rise(&s).unwrap();
+
+        assert_eq!(warning.index, 7);
+        assert_eq!(warning.since, s.points[3].0);
+        assert!((warning.before - 4.0 / 3.0).abs() < 1e-9);
+        assert_eq!(warning.after, 11.0);
+    }
+
+    #[test]
+    fn no_warning_for_a_flat_rate() {
+        assert!(check_rise(&series(&[(2, 50), (3, 50), (2, 50), (3, 50)])).is_none());
+    }
+
+    #[test]
+    fn no_warning_without_enough_boards_before() {
+        // 0% before, but only 10 boards were tested
+        assert!(check_rise(&series(&[(0, 5), (0, 5), (3, 5), (3, 5)])).is_none());
+        assert!(check_rise(&series(&[(0, 10), (0, 10), (3, 5), (3, 5)])).is_some());
+    }
+
+    #[test]
+    fn matrix_counts_failing_boards() {
+        let mut shorts = series(&[(1, 10), (2, 10)]);
+        shorts.family = TestFamily::Shorts;
+        let matrix = family_matrix(&[series(&[(1, 10), (0, 10), (4, 10)]), shorts]);
+
+        assert_eq!(matrix[&7], [5, 3, 0, 0, 0, 0]);
+    }
+}